@@ -114,6 +114,9 @@ impl ApplicationHandler<UserEvent> for EditorWindow{
             },
             WindowEvent::CloseRequested => {
                 println!("The close button was pressed; stopping");
+                if let Some(layout) = &mut self.egui_layout{
+                    layout.save_tree_state();
+                }
                 event_loop.exit();
             },
             WindowEvent::RedrawRequested => {
@@ -152,7 +155,14 @@ impl ApplicationHandler<UserEvent> for EditorWindow{
             UserEvent::KeyPress(key) => {
                 println!("User key press: {}", key)
             }
-            _ => println!("Unknown event: {:?}", event)
+            UserEvent::TreeLayer { .. } | UserEvent::TreeBuilt { .. } => {
+                if let Some(layout) = &mut self.egui_layout{
+                    layout.apply_build_event(event);
+                }
+                if let Some(window) = &self.window{
+                    window.request_redraw();
+                }
+            }
         }
     }
 }
@@ -181,9 +191,9 @@ impl EditorWindow{
         let full_output = egui_context.run(raw_input.clone(), |ctx| {
             //Top Panel must be build first and seperately from others.
             //Collect *UI* Changes performed in top menu
+            let mut menu = EditorMenu::new(egui_layout.get_history(), egui_layout.get_project_root(), egui_layout.get_workspace_roots());
             let ui_changes = egui::TopBottomPanel::top("MenuBar").show(ctx, |ui| {
                 if let Ok(mut settings) = editor_settings.try_write(){ 
-                    let mut menu = EditorMenu{};
                     menu.ui(ui, &mut settings)
                 }
                 else {
@@ -200,6 +210,16 @@ impl EditorWindow{
                     );
                 }
             }
+            //Workspace folders edited in the menu
+            if let Some(roots) = menu.take_workspace_roots(){
+                if let Ok(settings) = editor_settings.try_read(){
+                    egui_layout.set_workspace_roots(roots, &settings);
+                }
+            }
+            //Undo/redo requested from menu
+            if let Some(command) = menu.take_history_command(){
+                egui_layout.apply_history_command(command);
+            }
             //Central panel must be build last. 
             // Build layout UI here to avoid borrowing issues.
             // This consists of *ALL* the panels/tiles that exist inside the layout.
@@ -1,17 +1,23 @@
-use std::{collections::HashSet, path::PathBuf};
+use std::{collections::HashSet, path::PathBuf, time::{Duration, Instant}};
 
 use egui::{Pos2, Ui};
 use egui_tiles::{TileId, Tree};
 
-use crate::{core::editor::objects::{settings::{EditorSettings, FileTreeSettings}, ui_tree::{create_tree, Pane, PaneKind, PaneType, TreeBehavior}}, utils::error::EditorIoError};
+use crate::{core::editor::objects::{history::{HistoryCommand, HistorySummary}, settings::{self, EditorSettings, FileTreeSettings, TreeState}, ui_tree::{create_tree, Pane, PaneKind, PaneType, TreeBehavior}}, event::UserEvent, utils::error::EditorIoError};
 
 
+///Interval between checks of the file tree state, saved when changed.
+const STATE_SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
 ///This struct is an abstraction over Tiles/Panes and general
 /// UI handling of lower level UI entities.
 #[derive(Clone)]
 pub(crate) struct EditorLayout{
     tree: Tree<Pane>,
-    pub(crate) dropped_files: Vec<PathBuf>
+    pub(crate) dropped_files: Vec<PathBuf>,
+    //Last saved file tree state, and when it was last compared
+    saved_state: Option<TreeState>,
+    state_checked: Instant
 }
 impl EditorLayout{
     pub(crate) fn new(editor_settings: EditorSettings) -> Result<EditorLayout, EditorIoError>{
@@ -19,7 +25,9 @@ impl EditorLayout{
             Ok(tree) => {
                 return Ok(EditorLayout{
                     tree: tree,
-                    dropped_files: Vec::new()
+                    dropped_files: Vec::new(),
+                    saved_state: None,
+                    state_checked: Instant::now()
                 })
             },
             Err(err) => return Err(err),
@@ -30,6 +38,39 @@ impl EditorLayout{
     pub(crate) fn ui(&mut self, ui: &mut Ui){
         let mut behavior = TreeBehavior{};
         self.tree.ui(&mut behavior, ui);
+
+        self.forward_diagnostics();
+        if self.state_checked.elapsed() >= STATE_SAVE_INTERVAL{
+            self.save_tree_state();
+        }
+    }
+
+    ///Moves the read failures of the file tree pane into the console pane.
+    fn forward_diagnostics(&mut self){
+        let mut messages = Vec::new();
+        for (_, tile) in self.tree.tiles.iter_mut(){
+            if let egui_tiles::Tile::Pane(pane) = tile{
+                if let Some(directory) = pane.get_directory_mut(){
+                    messages.extend(directory.take_diagnostics().iter().map(|diagnostic| diagnostic.to_string()));
+                }
+            }
+        }
+        if messages.is_empty(){
+            return;
+        }
+
+        for (_, tile) in self.tree.tiles.iter_mut(){
+            if let egui_tiles::Tile::Pane(pane) = tile{
+                if let Some(console) = pane.get_messages_mut(){
+                    console.extend(messages);
+                    return;
+                }
+            }
+        }
+        //No console pane
+        for message in messages{
+            eprintln!("{}", message);
+        }
     }
 
     ///Reloads panes with new settings provided by menu.
@@ -64,6 +105,9 @@ impl EditorLayout{
                             PaneType::FileTree { .. } => {
                                 let new_settings = FileTreeSettings::from(settings.clone());
 
+                                if let Some(directory) = pane.get_directory_mut(){
+                                    directory.sync_workspace(settings);
+                                }
                                 pane.reload_with_settings(new_settings);
                             },
                             PaneType::Inspector { variables, new_key, new_value } => todo!(),
@@ -78,6 +122,107 @@ impl EditorLayout{
         }
     }
 
+    ///Operation history of the file tree pane, empty if there is none.
+    pub(crate) fn get_history(&self) -> HistorySummary{
+        for (_, tile) in self.tree.tiles.iter(){
+            if let egui_tiles::Tile::Pane(pane) = tile{
+                if let Some(directory) = pane.get_directory(){
+                    return directory.get_history()
+                }
+            }
+        }
+
+        return HistorySummary::default()
+    }
+
+    ///Project root of the file tree pane, None if there is none.
+    pub(crate) fn get_project_root(&self) -> Option<PathBuf>{
+        for (_, tile) in self.tree.tiles.iter(){
+            if let egui_tiles::Tile::Pane(pane) = tile{
+                if let Some(directory) = pane.get_directory(){
+                    return Some(directory.get_root().to_path_buf())
+                }
+            }
+        }
+
+        return None
+    }
+
+    ///Workspace folders of the file tree pane, empty if there is none.
+    pub(crate) fn get_workspace_roots(&self) -> Vec<PathBuf>{
+        for (_, tile) in self.tree.tiles.iter(){
+            if let egui_tiles::Tile::Pane(pane) = tile{
+                if let Some(directory) = pane.get_directory(){
+                    return directory.get_workspace_roots()
+                }
+            }
+        }
+
+        return Vec::new()
+    }
+
+    ///Mounts and unmounts workspace folders of the file tree pane, saving the mounted ones into its project file.
+    pub(crate) fn set_workspace_roots(&mut self, roots: Vec<PathBuf>, settings: &EditorSettings){
+        for (_, tile) in self.tree.tiles.iter_mut(){
+            if let egui_tiles::Tile::Pane(pane) = tile{
+                if let Some(directory) = pane.get_directory_mut(){
+                    directory.set_workspace_roots(&roots, settings);
+
+                    if let Err(err) = settings::save_workspace_roots(directory.get_root(), &directory.get_workspace_roots()){
+                        eprintln!("Failed saving workspace folders: {}", err);
+                    }
+                    return;
+                }
+            }
+        }
+    }
+
+    ///Saves the expansion, selection and scroll state of the file tree pane into its project file,
+    /// restored on the next launch. Unchanged states are not written again.
+    pub(crate) fn save_tree_state(&mut self){
+        self.state_checked = Instant::now();
+
+        for (_, tile) in self.tree.tiles.iter(){
+            if let egui_tiles::Tile::Pane(pane) = tile{
+                if let Some(directory) = pane.get_directory(){
+                    let state = directory.get_state();
+                    if self.saved_state.as_ref() == Some(&state){
+                        return;
+                    }
+
+                    match settings::save_tree_state(directory.get_root(), &state){
+                        Ok(_) => self.saved_state = Some(state),
+                        Err(err) => eprintln!("Failed saving file tree state: {}", err),
+                    }
+                    return;
+                }
+            }
+        }
+    }
+
+    ///Passes a background build event to the file tree pane.
+    pub(crate) fn apply_build_event(&mut self, event: UserEvent){
+        for (_, tile) in self.tree.tiles.iter_mut(){
+            if let egui_tiles::Tile::Pane(pane) = tile{
+                if let Some(directory) = pane.get_directory_mut(){
+                    directory.apply_build_event(event);
+                    return;
+                }
+            }
+        }
+    }
+
+    ///Undoes or redoes the last operation of the file tree pane.
+    pub(crate) fn apply_history_command(&mut self, command: HistoryCommand){
+        for (_, tile) in self.tree.tiles.iter_mut(){
+            if let egui_tiles::Tile::Pane(pane) = tile{
+                if let Some(directory) = pane.get_directory_mut(){
+                    directory.apply_history_command(command);
+                    return;
+                }
+            }
+        }
+    }
     
     pub(crate) fn file_hovered(&mut self, file: PathBuf){
         self.dropped_files.push(file.to_path_buf());
@@ -1,21 +1,107 @@
+use std::path::{Path, PathBuf};
+
 use egui::Ui;
 
-use crate::core::editor::objects::{settings::EditorSettings, ui_tree::PaneKind};
+use crate::{core::editor::objects::{history::{HistoryCommand, HistorySummary}, settings::EditorSettings, sort::SortMode, ui_tree::PaneKind}, utils::{io, rules::SymlinkPolicy}};
+
 
+///Number of operations listed in the *Edit > History* menu.
+const HISTORY_MENU_LEN: usize = 15;
 
-pub(crate) struct EditorMenu{}
+pub(crate) struct EditorMenu{
+    //File tree operation history
+    history: HistorySummary,
+    history_command: Option<HistoryCommand>,
+    //Project root of the file tree, which added folders are resolved against
+    project_root: Option<PathBuf>,
+    //Workspace folders of the file tree, and whether they were edited
+    workspace_roots: Vec<PathBuf>,
+    workspace_changed: bool
+}
 
 impl EditorMenu{
+    pub(crate) fn new(history: HistorySummary, project_root: Option<PathBuf>, workspace_roots: Vec<PathBuf>) -> EditorMenu{
+        return EditorMenu { 
+            history: history, 
+            history_command: None,
+            project_root: project_root,
+            workspace_roots: workspace_roots,
+            workspace_changed: false
+        }
+    }
+
+    ///Takes the undo/redo command requested in the menu, if any.
+    pub(crate) fn take_history_command(&mut self) -> Option<HistoryCommand>{
+        return self.history_command.take()
+    }
+
+    ///Takes the workspace folders if they were edited in the menu.
+    pub(crate) fn take_workspace_roots(&mut self) -> Option<Vec<PathBuf>>{
+        if !std::mem::replace(&mut self.workspace_changed, false){
+            return None
+        }
+
+        return Some(std::mem::take(&mut self.workspace_roots))
+    }
+
     ///Collects `PaneKinds` of which settings have been altered.
     pub(crate) fn ui(&mut self, ui: &mut Ui, settings: &mut EditorSettings) -> Option<Vec<PaneKind>>{
         let mut ui_changes = vec![];
 
         egui::menu::bar(ui, |ui| {
+            ui.menu_button("Edit", |ui| {
+                let undo = self.history.undo.first();
+                let undo_text = undo.map(|label| format!("Undo {}", label)).unwrap_or("Undo".to_string());
+                if ui.add_enabled(undo.is_some(), egui::Button::new(undo_text).shortcut_text("Ctrl+Z")).clicked(){
+                    self.history_command = Some(HistoryCommand::Undo);
+                    ui.close_menu();
+                }
+
+                let redo = self.history.redo.first();
+                let redo_text = redo.map(|label| format!("Redo {}", label)).unwrap_or("Redo".to_string());
+                if ui.add_enabled(redo.is_some(), egui::Button::new(redo_text).shortcut_text("Ctrl+Shift+Z")).clicked(){
+                    self.history_command = Some(HistoryCommand::Redo);
+                    ui.close_menu();
+                }
+                ui.separator();
+
+                //Newest first
+                ui.menu_button("History", |ui| {
+                    if self.history.undo.is_empty(){
+                        ui.weak("No operations");
+                    }
+                    for label in self.history.undo.iter().take(HISTORY_MENU_LEN){
+                        ui.label(label);
+                    }
+                });
+            });
             ui.menu_button("Settings", |ui| {
                 ui.menu_button("File Tree", |ui| {
                     if ui.checkbox(&mut settings.show_hidden_elements, "Show Hidden items").clicked(){
                         ui_changes.push(PaneKind::FileTree);
                     }
+                    if ui.checkbox(&mut settings.show_ignored_elements, "Show Ignored items").clicked(){
+                        ui_changes.push(PaneKind::FileTree);
+                    }
+                    ui.menu_button("Sort by", |ui| {
+                        for mode in SortMode::ALL{
+                            if ui.radio_value(&mut settings.sort_mode, mode, mode.to_string()).clicked(){
+                                ui_changes.push(PaneKind::FileTree);
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                    ui.menu_button("Symbolic links", |ui| {
+                        for policy in SymlinkPolicy::ALL{
+                            if ui.radio_value(&mut settings.symlink_policy, policy, policy.to_string()).clicked(){
+                                ui_changes.push(PaneKind::FileTree);
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                    ui.menu_button("Workspace Folders", |ui| {
+                        self.workspace_changed |= self.workspace_ui(ui);
+                    });
                     if ui.button("Show Hidden items").clicked() {
                         // handle action
                     }
@@ -32,4 +118,50 @@ impl EditorMenu{
 
         ui_changes.into()
     }
+
+    ///Lists the workspace folders shown next to the project root, with inputs to add or remove them.
+    /// Returns true if the list changed.
+    fn workspace_ui(&mut self, ui: &mut Ui) -> bool{
+        let mut changed = false;
+
+        if self.workspace_roots.is_empty(){
+            ui.weak("No workspace folders");
+        }
+        let mut removed = None;
+        for (index, root) in self.workspace_roots.iter().enumerate(){
+            ui.horizontal(|ui| {
+                ui.label(root.display().to_string());
+                if ui.small_button("✖").on_hover_text("Remove folder").clicked(){
+                    removed = Some(index);
+                }
+            });
+        }
+        if let Some(index) = removed{
+            self.workspace_roots.remove(index);
+            changed = true;
+        }
+        ui.separator();
+
+        //Input buffer is kept in egui memory, since the menu is recreated every frame
+        let input_id = ui.id().with("workspace_folder_input");
+        let mut input: String = ui.data_mut(|data| data.get_temp(input_id).unwrap_or_default());
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut input).hint_text("Folder path"));
+
+            //Resolved the way the file tree mounts it
+            let path = self.project_root
+                .as_ref()
+                .filter(|_| !input.trim().is_empty())
+                .map(|project_root| io::resolve_path(project_root, Path::new(input.trim())))
+                .filter(|path| path.is_dir() && !self.workspace_roots.iter().any(|root| io::resolve_path(root, Path::new("")) == *path));
+            if ui.add_enabled(path.is_some(), egui::Button::new("Add")).clicked(){
+                self.workspace_roots.extend(path);
+                input.clear();
+                changed = true;
+            }
+        });
+        ui.data_mut(|data| data.insert_temp(input_id, input));
+
+        return changed
+    }
 }
\ No newline at end of file
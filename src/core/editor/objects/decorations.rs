@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+
+use egui::Color32;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::io::{FileEntry, LinkStatus};
+
+
+///Icon and optional color of a file tree row or badge.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Decoration{
+    pub(crate) icon: String,
+    ///RGB color of the icon, default text color if None.
+    #[serde(default)]
+    pub(crate) color: Option<[u8; 3]>
+}
+impl Decoration{
+    fn new(icon: &str, color: Option<[u8; 3]>) -> Decoration{
+        return Decoration {
+            icon: icon.to_string(),
+            color: color
+        }
+    }
+
+    pub(crate) fn get_color(&self) -> Option<Color32>{
+        return self.color.map(|[r, g, b]| Color32::from_rgb(r, g, b))
+    }
+}
+
+///Status shown next to a file tree row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Badge{
+    ///Changed on disk since the editor was opened.
+    Modified,
+    Ignored,
+    Symlink(LinkStatus),
+    ///Last operation or read of the node failed.
+    Error(String)
+}
+impl Badge{
+    ///Settings key of the badge decoration.
+    fn get_key(&self) -> &'static str{
+        match self{
+            Badge::Modified => return "@modified",
+            Badge::Ignored => return "@ignored",
+            Badge::Symlink(LinkStatus::Followed | LinkStatus::NotFollowed) => return "@symlink",
+            Badge::Symlink(LinkStatus::Cycle) => return "@symlink_loop",
+            Badge::Symlink(LinkStatus::Broken) => return "@broken_link",
+            Badge::Error(_) => return "@error",
+        }
+    }
+
+    pub(crate) fn get_tooltip(&self) -> String{
+        match self{
+            Badge::Modified => return "Modified".to_string(),
+            Badge::Ignored => return "Ignored".to_string(),
+            Badge::Symlink(LinkStatus::Followed) => return "Symbolic link".to_string(),
+            Badge::Symlink(LinkStatus::NotFollowed) => return "Symbolic link, not followed".to_string(),
+            Badge::Symlink(LinkStatus::Cycle) => return "Symbolic link to a parent directory, not followed".to_string(),
+            Badge::Symlink(LinkStatus::Broken) => return "Broken symbolic link".to_string(),
+            Badge::Error(message) => return format!("Error: {}", message),
+        }
+    }
+}
+
+///Maps entries to their decorations. Keys are exact entry names (`Cargo.toml`), extensions (`*.png`)
+/// or badges (`@modified`, `@ignored`, `@symlink`, `@symlink_loop`, `@broken_link`, `@error`). Names take precedence over extensions.
+#[derive(Debug, Clone)]
+pub(crate) struct DecorationRegistry{
+    names: HashMap<String, Decoration>,
+    //Lowercase, without the dot
+    extensions: HashMap<String, Decoration>,
+    badges: HashMap<String, Decoration>,
+    directory: Decoration,
+    file: Decoration
+}
+impl DecorationRegistry{
+    ///Creates the registry with the built-in decorations, replaced or extended by `overrides`.
+    pub(crate) fn new(overrides: &HashMap<String, Decoration>) -> DecorationRegistry{
+        let mut registry = DecorationRegistry {
+            names: HashMap::new(),
+            extensions: HashMap::new(),
+            badges: HashMap::new(),
+            directory: Decoration::new("📁", None),
+            file: Decoration::new("📄", None)
+        };
+
+        let defaults = [
+            ("Cargo.toml", "📦", Some([222, 165, 132])),
+            ("Cargo.lock", "🔒", Some([222, 165, 132])),
+            (".gitignore", "🙈", None),
+            ("*.rs", "🦀", Some([222, 165, 132])),
+            ("*.toml", "⚙", None),
+            ("*.json", "📋", Some([203, 203, 65])),
+            ("*.md", "📝", Some([81, 154, 186])),
+            ("*.txt", "📝", None),
+            ("*.wgsl", "🎨", Some([160, 116, 196])),
+            ("*.png", "🖼", Some([160, 116, 196])),
+            ("*.jpg", "🖼", Some([160, 116, 196])),
+            ("*.jpeg", "🖼", Some([160, 116, 196])),
+            ("*.gif", "🖼", Some([160, 116, 196])),
+            ("*.scene", "🎬", Some([141, 193, 73])),
+            ("*.ron", "🎬", Some([141, 193, 73])),
+            ("@modified", "●", Some([226, 192, 141])),
+            ("@ignored", "⊘", Some([128, 128, 128])),
+            ("@symlink", "🔗", Some([81, 154, 186])),
+            ("@symlink_loop", "🔁", Some([226, 192, 141])),
+            ("@broken_link", "🔗", Some([224, 108, 117])),
+            ("@error", "⚠", Some([224, 108, 117]))
+        ];
+        for (key, icon, color) in defaults{
+            registry.register(key, Decoration::new(icon, color));
+        }
+        for (key, decoration) in overrides{
+            registry.register(key, decoration.clone());
+        }
+
+        return registry
+    }
+
+    ///Adds or replaces the decoration of a name, `*.extension` or `@badge` key.
+    pub(crate) fn register(&mut self, key: &str, decoration: Decoration){
+        if let Some(extension) = key.strip_prefix("*."){
+            self.extensions.insert(extension.to_lowercase(), decoration);
+        }
+        else if key.starts_with('@'){
+            self.badges.insert(key.to_string(), decoration);
+        }
+        else{
+            self.names.insert(key.to_string(), decoration);
+        }
+    }
+
+    ///Returns the decoration of an entry. Directories only match by name.
+    pub(crate) fn get(&self, entry: &FileEntry) -> &Decoration{
+        if let Some(decoration) = self.names.get(&entry.name){
+            return decoration
+        }
+        if entry.is_dir{
+            return &self.directory
+        }
+
+        return entry.path
+            .extension()
+            .and_then(|extension| self.extensions.get(&extension.to_string_lossy().to_lowercase()))
+            .unwrap_or(&self.file)
+    }
+
+    pub(crate) fn get_badge(&self, badge: &Badge) -> Option<&Decoration>{
+        return self.badges.get(badge.get_key())
+    }
+}
+
+impl Default for DecorationRegistry{
+    fn default() -> DecorationRegistry {
+        return DecorationRegistry::new(&HashMap::new())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs};
+
+    /*
+        Validates lookup precedence (name, extension, default) and that overrides
+        from settings replace built-in decorations and add new ones.
+    */
+    #[test]
+    fn test_registry_lookup() {
+        let test_dir = env::temp_dir().join("reasy_test_registry_lookup");
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(test_dir.join("assets")).unwrap();
+        for name in ["Cargo.toml", "main.RS", "shader.wgsl", "data.xyz", "notes"]{
+            fs::write(test_dir.join(name), "").unwrap();
+        }
+        let entry = |name: &str| {
+            let path = test_dir.join(name);
+            FileEntry::new(path.clone(), fs::symlink_metadata(path).unwrap())
+        };
+
+        let mut overrides = HashMap::new();
+        overrides.insert("*.wgsl".to_string(), Decoration::new("S", None));
+        overrides.insert("*.xyz".to_string(), Decoration::new("X", Some([1, 2, 3])));
+        overrides.insert("assets".to_string(), Decoration::new("A", None));
+        let registry = DecorationRegistry::new(&overrides);
+
+        assert_eq!(registry.get(&entry("Cargo.toml")).icon, "📦");
+        assert_eq!(registry.get(&entry("main.RS")).icon, "🦀");
+        assert_eq!(registry.get(&entry("shader.wgsl")).icon, "S");
+        assert_eq!(registry.get(&entry("data.xyz")).get_color(), Some(Color32::from_rgb(1, 2, 3)));
+        assert_eq!(registry.get(&entry("notes")).icon, "📄");
+        assert_eq!(registry.get(&entry("assets")).icon, "A");
+        assert_eq!(registry.get_badge(&Badge::Error("failed".to_string())).unwrap().icon, "⚠");
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+}
@@ -0,0 +1,97 @@
+use core::fmt;
+
+use glob::{MatchOptions, Pattern};
+use regex::{Regex, RegexBuilder};
+
+
+///How the filter query is interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum FilterMode{
+    #[default]
+    Substring,
+    Glob,
+    Regex
+}
+
+impl fmt::Display for FilterMode{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self{
+            FilterMode::Substring => write!(f, "Text"),
+            FilterMode::Glob => write!(f, "Glob"),
+            FilterMode::Regex => write!(f, "Regex"),
+        }
+    }
+}
+
+///Compiled form of the filter query.
+#[derive(Debug, Clone)]
+enum FilterMatcher{
+    Substring(String),
+    Glob(Pattern),
+    Regex(Regex)
+}
+
+///Filter that restricts the file tree to nodes whose names match `query`. Matching is case-insensitive.
+/// `update` must be called after changing the query or mode.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TreeFilter{
+    pub(crate) query: String,
+    pub(crate) mode: FilterMode,
+    matcher: Option<FilterMatcher>,
+    ///Error of an invalid query.
+    pub(crate) error: Option<String>
+}
+impl TreeFilter{
+    ///Compiles the query. Empty or invalid queries deactivate the filter.
+    pub(crate) fn update(&mut self){
+        self.matcher = None;
+        self.error = None;
+
+        if self.query.is_empty(){
+            return;
+        }
+
+        let matcher = match self.mode{
+            FilterMode::Substring => Ok(FilterMatcher::Substring(self.query.to_lowercase())),
+            FilterMode::Glob => Pattern::new(&self.query)
+                .map(FilterMatcher::Glob)
+                .map_err(|err| err.to_string()),
+            FilterMode::Regex => RegexBuilder::new(&self.query)
+                .case_insensitive(true)
+                .build()
+                .map(FilterMatcher::Regex)
+                .map_err(|err| err.to_string()),
+        };
+
+        match matcher{
+            Ok(matcher) => self.matcher = Some(matcher),
+            Err(err) => self.error = Some(err),
+        }
+    }
+
+    ///Clears the query, deactivating the filter.
+    pub(crate) fn clear(&mut self){
+        self.query.clear();
+        self.update();
+    }
+
+    ///Returns true if the filter has a valid query.
+    pub(crate) fn is_active(&self) -> bool{
+        return self.matcher.is_some()
+    }
+
+    pub(crate) fn matches(&self, name: &str) -> bool{
+        match &self.matcher{
+            Some(FilterMatcher::Substring(query)) => return name.to_lowercase().contains(query),
+            Some(FilterMatcher::Glob(pattern)) => {
+                let options = MatchOptions{
+                    case_sensitive: false,
+                    ..MatchOptions::default()
+                };
+                return pattern.matches_with(name, options)
+            },
+            Some(FilterMatcher::Regex(regex)) => return regex.is_match(name),
+            None => return true,
+        }
+    }
+}
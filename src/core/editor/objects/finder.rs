@@ -0,0 +1,70 @@
+use crate::{core::editor::objects::{flat_tree::FlatTree, settings::FileTreeSettings}, utils::fuzzy::{self, FuzzyMatch}};
+
+///Maximum number of results kept by the finder.
+const MAX_RESULTS: usize = 50;
+
+
+///Single ranked result of the file finder.
+#[derive(Debug, Clone)]
+pub(crate) struct FinderResult{
+    pub(crate) id: usize,
+    pub(crate) path: String,
+    pub(crate) matched: FuzzyMatch
+}
+
+///Fuzzy file finder state. Scores every node path of a FlatTree shown by the pane against `query`
+/// and keeps the best ranked results.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FileFinder{
+    pub(crate) query: String,
+    pub(crate) results: Vec<FinderResult>,
+    pub(crate) selected: usize
+}
+impl FileFinder{
+    ///Recomputes results for the current query. Hidden and ignored nodes are only searched if `settings` shows them.
+    pub(crate) fn search(&mut self, tree: &FlatTree, settings: &FileTreeSettings){
+        let mut results = tree.get_relative_paths(|entry| settings.shows(entry))
+            .into_iter()
+            .filter_map(|(id, path)| {
+                fuzzy::fuzzy_match(&self.query, &path)
+                    .map(|matched| FinderResult { id: id, path: path, matched: matched })
+            })
+            .collect::<Vec<FinderResult>>();
+
+        //Best score first, shorter paths first on ties
+        results.sort_by(|a, b| {
+            b.matched.score.cmp(&a.matched.score)
+                .then(a.path.len().cmp(&b.path.len()))
+                .then(a.path.cmp(&b.path))
+        });
+        results.truncate(MAX_RESULTS);
+
+        self.results = results;
+        self.selected = 0;
+    }
+
+    ///Recomputes results after the tree changed, keeping the selected result if still found.
+    pub(crate) fn update(&mut self, tree: &FlatTree, settings: &FileTreeSettings){
+        let selected = self.get_selected();
+        self.search(tree, settings);
+
+        if let Some(index) = selected.and_then(|id| self.results.iter().position(|result| result.id == id)){
+            self.selected = index;
+        }
+    }
+
+    pub(crate) fn select_next(&mut self){
+        if self.selected + 1 < self.results.len(){
+            self.selected += 1;
+        }
+    }
+
+    pub(crate) fn select_previous(&mut self){
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    ///Returns the node id of the selected result.
+    pub(crate) fn get_selected(&self) -> Option<usize>{
+        return self.results.get(self.selected).map(|result| result.id)
+    }
+}
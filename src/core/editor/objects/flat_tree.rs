@@ -1,15 +1,17 @@
-use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::cell::{Ref, RefCell};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::Debug;
-use std::hash::{DefaultHasher, Hasher};
 use std::sync::Arc;
 use std::{env, fmt};
 use std::path::{Path, PathBuf};
-use std::hash::Hash;
 
-use crate::utils::error::ErrorType;
-use crate::utils::{error::EditorIoError, io::FileEntry};
+use crate::utils::error::{ErrorType, NameError};
+use crate::utils::{error::EditorIoError, io::{DirectoryRead, FileEntry, ReadError}};
 use crate::utils::io;
+use crate::utils::rules::EntryRules;
+use crate::core::editor::objects::filter::TreeFilter;
+use crate::core::editor::objects::settings::EditorSettings;
+use crate::core::editor::objects::sort::SortMode;
 use crate::EDITOR_ROOT_DIR;
 
 
@@ -22,7 +24,15 @@ pub(crate) struct TreeNode{
     children: Vec<usize>,
     pub(crate) parent: usize,
     pub(crate) visible: bool,
-    pub(crate) expanded: bool
+    pub(crate) expanded: bool,
+    loaded: bool,
+    loading: bool
+}
+impl TreeNode{
+    ///Returns true if the node is a directory whose contents are being read.
+    pub(crate) fn is_loading(&self) -> bool{
+        return self.loading
+    }
 }
 
 impl fmt::Debug for TreeNode{
@@ -48,11 +58,7 @@ impl PartialOrd for TreeNode{
 
 impl Ord for TreeNode{
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        match (self.file_entry.is_dir, other.file_entry.is_dir) {
-            (true, false) => return Ordering::Less,
-            (false, true) => return Ordering::Greater,
-            _ => self.file_entry.name.cmp(&other.file_entry.name)
-        }
+        return SortMode::Name.compare(&self.file_entry, &other.file_entry)
     }
 }
 
@@ -65,20 +71,312 @@ impl PartialEq for TreeNode{
 impl Eq for TreeNode{}
 
 
+///Stable node identities. Ids are allocated from a counter the first time a path is seen, never
+/// reused for another path, and follow their path through renames and moves.
+/// Paths of removed nodes keep their id, so that restored or re-inserted entries get it back, until pruned.
+#[derive(Debug, Clone)]
+pub(crate) struct NodeIds{
+    //Ordered by components, a path is directly followed by the paths below it
+    paths: BTreeMap<PathBuf, usize>,
+    ids: HashMap<usize, PathBuf>,
+    //`0` is reserved for the tree root
+    next: usize
+}
+impl NodeIds{
+    pub(crate) fn new() -> NodeIds{
+        return NodeIds {
+            paths: BTreeMap::new(),
+            ids: HashMap::new(),
+            next: 1
+        }
+    }
+
+    ///Returns the id of a path, allocating a new one if the path was never seen.
+    fn get_or_allocate(&mut self, path: &Path) -> usize{
+        if let Some(id) = self.paths.get(path){
+            return *id
+        }
 
+        let id = self.next;
+        self.next += 1;
+        self.assign(path, id);
+
+        return id
+    }
+
+    ///Binds a path to an id, releasing the previous path of the id and the previous id of the path.
+    fn assign(&mut self, path: &Path, id: usize){
+        if let Some(old_path) = self.ids.insert(id, path.to_path_buf()){
+            if old_path != path{
+                self.paths.remove(&old_path);
+            }
+        }
+        if let Some(old_id) = self.paths.insert(path.to_path_buf(), id){
+            if old_id != id{
+                self.ids.remove(&old_id);
+            }
+        }
+    }
+
+    fn get(&self, path: &Path) -> Option<usize>{
+        return self.paths.get(path).copied()
+    }
+
+    fn get_path(&self, id: usize) -> Option<&Path>{
+        return self.ids.get(&id).map(|path| path.as_path())
+    }
+
+    ///Ids of a path and of every path below it.
+    fn get_subtree(&self, path: &Path) -> Vec<(PathBuf, usize)>{
+        return self.paths
+            .range(path.to_path_buf()..)
+            .take_while(|(subpath, _)| subpath.starts_with(path))
+            .map(|(subpath, id)| (subpath.clone(), *id))
+            .collect()
+    }
+
+    ///Moves the ids of a path and of every path below it to a new location.
+    /// Returns the ids that were bound to the new location, which are released.
+    fn rename(&mut self, from: &Path, to: &Path) -> Vec<usize>{
+        if from == to{
+            return Vec::new()
+        }
+        let moved = self.get_subtree(from);
+
+        for (path, _) in &moved{
+            self.paths.remove(path);
+        }
+        let overwritten: Vec<usize> = self.get_subtree(to)
+            .into_iter()
+            .map(|(path, id)| {
+                self.paths.remove(&path);
+                self.ids.remove(&id);
+                id
+            })
+            .collect();
+
+        for (path, id) in moved{
+            if let Ok(relative) = path.strip_prefix(from){
+                let new_path = if relative.as_os_str().is_empty(){ to.to_path_buf() } else { to.join(relative) };
+                self.paths.insert(new_path.clone(), id);
+                self.ids.insert(id, new_path);
+            }
+        }
+
+        return overwritten
+    }
+
+    ///Forgets the ids for which `keep` returns false. Their paths get new ids if seen again.
+    fn retain(&mut self, keep: impl Fn(usize) -> bool){
+        self.paths.retain(|_, id| keep(*id));
+        self.ids.retain(|id, _| keep(*id));
+    }
+
+}
+
+
+
+///Folder of the tree, with its own ignore rules.
+#[derive(Debug, Clone)]
+struct WorkspaceRoot{
+    path: PathBuf,
+    rules: EntryRules
+}
 
 ///FlatTree structure used in UI file tree.
 #[derive(Debug, Clone)]
 pub(crate) struct FlatTree{
     elements: Vec<TreeNode>,
-    lookup: HashMap<usize, usize>
+    lookup: HashMap<usize, usize>,
+    //Path to id map, outlives the nodes
+    ids: NodeIds,
+    //Project root first. A single root shows its contents at depth 0,
+    // several roots (workspace) are shown as named nodes at depth 0.
+    roots: Vec<WorkspaceRoot>,
+    //Directories are read on first expansion
+    lazy: bool,
+    //Directories waiting to be read
+    pending_loads: Vec<(usize, PathBuf)>,
+    //Entries and directories that failed to be read, taken by the UI
+    diagnostics: Vec<ReadError>,
+    //Order of siblings
+    sort_mode: SortMode,
+    //Cached display order as (id, subtree length) pairs, cleared on structural changes
+    order: RefCell<Option<Vec<(usize, usize)>>>,
+    //Set whenever visible items may have changed, taken by the UI to know when to recompute
+    dirty: bool
 }
 impl FlatTree{
-    fn new() -> FlatTree{
+    fn new(root: PathBuf, rules: EntryRules) -> FlatTree{
         return FlatTree{
             elements: Vec::new(),
-            lookup: HashMap::new()
+            lookup: HashMap::new(),
+            ids: NodeIds::new(),
+            roots: vec![WorkspaceRoot { path: root, rules: rules }],
+            lazy: false,
+            pending_loads: Vec::new(),
+            diagnostics: Vec::new(),
+            sort_mode: SortMode::default(),
+            order: RefCell::new(None),
+            dirty: true
+        }
+    }
+
+    ///Rules used to mark entries of the root containing `path` as hidden or ignored.
+    pub(crate) fn get_rules(&self, path: &Path) -> &EntryRules{
+        return &self.get_workspace_root(path).rules
+    }
+
+    ///Project root, the directory the tree was first built from.
+    pub(crate) fn get_root(&self) -> &Path{
+        return &self.roots[0].path
+    }
+
+    ///Every root folder of the tree, the project root first.
+    pub(crate) fn get_roots(&self) -> Vec<&Path>{
+        return self.roots.iter().map(|root| root.path.as_path()).collect()
+    }
+
+    ///Returns true if the tree has several roots, shown as named top-level nodes.
+    pub(crate) fn is_workspace(&self) -> bool{
+        return self.roots.len() > 1
+    }
+
+    ///Returns true if the node is the top-level node of a workspace folder.
+    pub(crate) fn is_workspace_root(&self, id: usize) -> bool{
+        return self.is_workspace() && self.get_node(id).is_some_and(|node| node.depth == 0)
+    }
+
+    ///Directory whose entries are shown at depth 0 (target `0`), None for workspaces.
+    pub(crate) fn get_top_level(&self) -> Option<&Path>{
+        if self.is_workspace(){
+            return None
+        }
+
+        return Some(self.get_root())
+    }
+
+    ///Returns the root containing `path`, the project root if none does.
+    fn get_workspace_root(&self, path: &Path) -> &WorkspaceRoot{
+        return self.roots
+            .iter()
+            .rev()
+            .find(|root| path.starts_with(&root.path))
+            .unwrap_or(&self.roots[0])
+    }
+
+    ///Path as shown to the user, relative to the tree root. Workspace paths start with their folder name.
+    pub(crate) fn get_display_path(&self, path: &Path) -> PathBuf{
+        let root = &self.get_workspace_root(path).path;
+        let relative = path.strip_prefix(root).unwrap_or(path);
+
+        if self.is_workspace(){
+            return root.file_name().map(|name| Path::new(name).join(relative)).unwrap_or(relative.to_path_buf())
         }
+        return relative.to_path_buf()
+    }
+
+    ///Adds the tree of another folder, making this tree a workspace where every folder is a named top-level node.
+    /// `tree` must be built with the ids of this tree (see `TreeBuilder::with_ids`), so that ids don't collide.
+    pub(crate) fn mount(&mut self, tree: FlatTree) -> Result<(), EditorIoError>{
+        //Allocations of the mounted tree continue from the ids of this tree
+        self.ids = tree.ids;
+
+        if !self.is_workspace(){
+            let root = self.get_root().to_path_buf();
+            let nodes = std::mem::take(&mut self.elements);
+            self.elements = self.wrap_root(&root, nodes)?;
+        }
+        let mounted = self.wrap_root(&tree.roots[0].path, tree.elements)?;
+
+        self.elements.extend(mounted);
+        self.roots.extend(tree.roots);
+        self.pending_loads.extend(tree.pending_loads);
+        self.diagnostics.extend(tree.diagnostics);
+        self.rebuild_index();
+
+        return Ok(())
+    }
+
+    ///Nests the top-level nodes of a folder under an expanded node of the folder itself.
+    fn wrap_root(&mut self, root: &Path, mut nodes: Vec<TreeNode>) -> Result<Vec<TreeNode>, EditorIoError>{
+        let id = self.ids.get_or_allocate(root);
+        //Root folders are never hidden nor ignored
+        let file_entry = FileEntry::new(root.to_path_buf(), std::fs::metadata(root)?);
+
+        let children = nodes.iter().filter(|node| node.depth == 0).map(|node| node.id).collect();
+        for node in nodes.iter_mut(){
+            if node.depth == 0{
+                node.parent = id;
+            }
+            node.depth += 1;
+        }
+
+        let root_node = TreeNode {
+            id: id,
+            depth: 0,
+            file_entry: file_entry,
+            children: children,
+            parent: 0,
+            visible: true,
+            expanded: true,
+            loaded: true,
+            loading: false
+        };
+        nodes.insert(0, root_node);
+
+        return Ok(nodes)
+    }
+
+    ///Removes a workspace folder and its nodes. When only the project root is left, its entries
+    /// are shown at depth 0 again. Returns false if `root` is not a mounted folder.
+    pub(crate) fn unmount(&mut self, root: &Path) -> bool{
+        let Some(index) = self.roots.iter().skip(1).position(|mounted| mounted.path == root) else{
+            return false
+        };
+        self.roots.remove(index + 1);
+        self.pending_loads.retain(|(_, path)| !path.starts_with(root));
+
+        if let Some(id) = self.find_by_path(root){
+            self.remove(id);
+        }
+
+        if !self.is_workspace(){
+            let project = self.get_root().to_path_buf();
+
+            if let Some(id) = self.find_by_path(&project){
+                //Children of a collapsed folder are hidden, they must be shown at the top level
+                self.expand(id);
+                self.elements.retain(|node| node.id != id);
+
+                for node in self.elements.iter_mut(){
+                    if node.parent == id{
+                        node.parent = 0;
+                    }
+                    node.depth -= 1;
+                }
+            }
+        }
+        self.rebuild_index();
+
+        return true
+    }
+
+    pub(crate) fn contains(&self, id: usize) -> bool{
+        return self.lookup.contains_key(&id)
+    }
+
+    ///Changes the order of siblings. The display order is recomputed only if the mode changed.
+    pub(crate) fn set_sort_mode(&mut self, sort_mode: SortMode){
+        if self.sort_mode != sort_mode{
+            self.sort_mode = sort_mode;
+            self.invalidate_order();
+        }
+    }
+
+    ///Path to id map of the tree, used to keep ids when rebuilding it.
+    pub(crate) fn get_ids(&self) -> &NodeIds{
+        return &self.ids
     }
 
     //Retrieve a mutable node reference via node id.
@@ -103,6 +401,7 @@ impl FlatTree{
 
     ///Rebuils lookup table
     fn rebuild_index(&mut self){
+        self.invalidate_order();
         self.lookup.clear();
 
         for (index, node) in self.elements.iter().enumerate(){
@@ -115,13 +414,13 @@ impl FlatTree{
     fn build(&mut self, directory: &Vec<FileEntry>){
         if self.elements.is_empty(){
             for file_entry in directory{
-                let id = Self::path_to_id(&file_entry.path);
+                let id = self.ids.get_or_allocate(&file_entry.path);
                 self.add_as_root(file_entry, id);
             }
         }
         else{
             for element in directory{
-                let id = Self::path_to_id(&element.path);
+                let id = self.ids.get_or_allocate(&element.path);
 
                 if self.lookup.contains_key(&id){
                     continue;
@@ -156,9 +455,12 @@ impl FlatTree{
             parent: pid,
             visible: false,
             expanded: false,
+            loaded: !(self.lazy && element.is_dir),
+            loading: false
         };
         self.elements.push(new_node);
-        self.lookup.insert(id, self.elements.len());
+        self.lookup.insert(id, self.elements.len() - 1);
+        self.invalidate_order();
     }
 
     ///Add entry as root.
@@ -171,102 +473,142 @@ impl FlatTree{
             parent: 0, 
             visible: true,
             expanded: false,
+            loaded: !(self.lazy && element.is_dir),
+            loading: false
         };
         self.elements.push(new_node);
-        self.lookup.insert(id, self.elements.len());
+        self.lookup.insert(id, self.elements.len() - 1);
+        self.invalidate_order();
     }
 
     ///Returns the parents index, id and depth based on entry *path*, if exists.
     fn get_parent_from_path(&self, path: &PathBuf) -> Option<(usize, usize, usize)>{
-        self.elements
+        let id = self.find_by_path(path)?;
+
+        return self.lookup.get(&id).map(|index| (*index, id, self.elements[*index].depth))
+    }
+
+    ///Drops the cached display order and flags the tree as changed, must be called whenever links or names change.
+    fn invalidate_order(&mut self){
+        self.order.borrow_mut().take();
+        self.dirty = true;
+    }
+
+    ///Returns true if the tree changed since the last call.
+    pub(crate) fn take_dirty(&mut self) -> bool{
+        return std::mem::replace(&mut self.dirty, false)
+    }
+
+    ///Returns the cached display order, walking the tree again if it was invalidated.
+    fn get_order(&self) -> Ref<'_, Vec<(usize, usize)>>{
+        if self.order.borrow().is_none(){
+            let order = self.build_order();
+            *self.order.borrow_mut() = Some(order);
+        }
+
+        return Ref::map(self.order.borrow(), |order| order.as_ref().unwrap())
+    }
+
+    ///Walks every node in pre-order (siblings sorted), pairing each id with the length of its subtree
+    /// so that collapsed directories can be skipped in a single step.
+    fn build_order(&self) -> Vec<(usize, usize)>{
+        let mut roots = self.elements
             .iter()
-            .enumerate()
-            .find(|(_, node)| node.file_entry.path == *path)
-            .map(|(index, node)| (index, node.id, node.depth))
+            .filter(|node| node.depth == 0)
+            .collect::<Vec<&TreeNode>>();
+        roots.sort_by(|first, second| self.sort_mode.compare(&first.file_entry, &second.file_entry));
+
+        let mut order: Vec<(usize, usize)> = Vec::with_capacity(self.elements.len());
+        //Order index and depth of nodes whose subtree is still being walked
+        let mut open: Vec<(usize, usize)> = Vec::new();
+        //Reversed so that the first sibling is popped first
+        let mut stack = roots.into_iter().rev().map(|node| (node, 0)).collect::<Vec<(&TreeNode, usize)>>();
+
+        while let Some((node, depth)) = stack.pop(){
+            //Open nodes at the same depth or deeper have been fully walked
+            while let Some(&(index, open_depth)) = open.last(){
+                if open_depth < depth{
+                    break;
+                }
+                order[index].1 = order.len() - index;
+                open.pop();
+            }
+
+            open.push((order.len(), depth));
+            order.push((node.id, 1));
+
+            let mut children = node.children
+                .iter()
+                .filter_map(|child| self.get_node(*child))
+                .collect::<Vec<&TreeNode>>();
+            children.sort_by(|first, second| self.sort_mode.compare(&first.file_entry, &second.file_entry));
+            stack.extend(children.into_iter().rev().map(|child| (child, depth + 1)));
+        }
+
+        for (index, _) in open{
+            order[index].1 = order.len() - index;
+        }
+
+        return order
     }
 
     ///Creates and returns a new Flat Tree structure that is prepared (ordered and sorted) 
-    /// to show in UI context.
+    /// to show in UI context. Subtrees of collapsed or invisible nodes are skipped.
     pub(crate) fn get_visible_items(&self) -> Vec<&TreeNode>{
+        let order = self.get_order();
         let mut structure: Vec<&TreeNode> = Vec::new();
+        let mut index = 0;
 
-        //Get max depth
-        let max_depth = self.elements
-            .iter()
-            .map(|entry| entry.depth)
-            .max()
-            .unwrap_or(0);
-
-        //Iterate entries by depth
-        for depth in 0..=max_depth{
-            // Group items by parent id
-            let mut parent_groups: HashMap<usize, Vec<&TreeNode>> = HashMap::new();
-            
-            // Collect all visible items at this depth and group them by parent
-            for node in self.elements.iter().filter(|entry| entry.depth == depth && entry.visible) {
-                parent_groups.entry(node.parent).or_insert_with(Vec::new).push(node);
-            }
-            
-            //Note: Don't break, in case of empty dir
-            if parent_groups.is_empty() {
-                continue;
-            }
-            //Sort based on TreeNodes
-            for items in parent_groups.values_mut() {
-                items.sort();
-            }
-            
-            // Process each parent group
-            for (parent_id, items) in parent_groups {
-                //Root level items (parent_id == 0) are always added
-                if parent_id == 0{
-                    structure.extend(items);
-                }
-                //Non-root item
-                else {
-                    //Check if parent is expanded
-                    let parent_expanded = self.get_node(parent_id)
-                        .map(|node| node.expanded)
-                        .unwrap_or(false);
-                    
-                    //If not expanded, skip these children
-                    if !parent_expanded{
-                        continue;
-                    }
+        while index < order.len(){
+            let (id, subtree_len) = order[index];
 
-                    // Default placement position
-                    let mut insert_pos = structure.len();
-                    
-                    // Find the parent position and assign the next spot
-                    if let Some(parent_pos) = structure.iter().position(|element| element.id == parent_id) {
-                        insert_pos = parent_pos + 1;
-                        
-                        // Find the last child of this parent that's already in the structure
-                        // Append after it to maintain an order... older -> newer
-                        for i in (parent_pos + 1)..structure.len() {
-                            if structure[i].parent == parent_id {
-                                insert_pos = i + 1;
-                            } 
-                            else {
-                                break;
-                            }
-                        }
-                    }
-                    
-                    // Insert items in order (regarding parents structure)
-                    for (i, item) in items.iter().enumerate() {
-                        structure.insert(insert_pos + i, item);
-                    }
+            match self.get_node(id){
+                Some(node) if node.visible => {
+                    structure.push(node);
+                    index += if node.expanded { 1 } else { subtree_len };
                 }
+                _ => index += subtree_len
             }
         }
         
         structure
     }
 
+    ///Returns every node in display order (pre-order, siblings sorted).
+    fn get_ordered_items(&self) -> Vec<&TreeNode>{
+        return self.get_order()
+            .iter()
+            .filter_map(|(id, _)| self.get_node(*id))
+            .collect()
+    }
+
+    ///Returns the nodes to display while filtering, in display order. Keeps nodes whose name matches
+    /// the filter and all of their ancestors, regardless of their `visible` and `expanded` flags.
+    pub(crate) fn get_filtered_items(&self, filter: &TreeFilter) -> Vec<&TreeNode>{
+        let mut kept = HashSet::new();
+
+        for node in self.elements.iter().filter(|node| filter.matches(&node.file_entry.name)){
+            let mut current = Some(node.id);
+
+            //Keep node and ancestors, stop at already kept ancestors
+            while let Some(id) = current{
+                if !kept.insert(id){
+                    break;
+                }
+                current = self.get_parent_id(id);
+            }
+        }
+
+        return self.get_ordered_items()
+            .into_iter()
+            .filter(|node| kept.contains(&node.id))
+            .collect()
+    }
+
     //Toggle visibility of a directory.
     pub(crate) fn toggle_visibility(&mut self, id: &usize) {
         if let Some(node) = self.elements.iter_mut().find(|entry| entry.id == *id) {
+            self.dirty = true;
             let is_visible = node.visible;
             let is_expanded = node.expanded;
             let children_id = node.children.clone();
@@ -280,6 +622,12 @@ impl FlatTree{
             //Show children
             else if is_visible && !is_expanded {
                 node.expanded = true;
+
+                //Queue unloaded directory for reading
+                if !node.loaded && !node.loading{
+                    node.loading = true;
+                    self.pending_loads.push((node.id, node.file_entry.path.clone()));
+                }
                 self.toggle_children(&children_id, Some(true));
             } 
             //Hide self and children
@@ -291,6 +639,178 @@ impl FlatTree{
         }
     }
 
+    ///Expands every ancestor of a node, making it visible.
+    pub(crate) fn reveal(&mut self, id: usize){
+        let mut ancestors = Vec::new();
+        let mut current = self.get_parent_id(id);
+
+        while let Some(ancestor) = current{
+            ancestors.push(ancestor);
+            current = self.get_parent_id(ancestor);
+        }
+
+        //Expand from root to node
+        for ancestor in ancestors.into_iter().rev(){
+            self.expand(ancestor);
+        }
+    }
+
+    ///Returns the paths of the expanded directories in display order, parents before children.
+    pub(crate) fn get_expanded_paths(&self) -> Vec<PathBuf>{
+        return self.get_visible_items()
+            .into_iter()
+            .filter(|node| node.file_entry.is_dir && node.expanded)
+            .map(|node| node.file_entry.path.clone())
+            .collect()
+    }
+
+    ///Returns true while any directory is being read.
+    pub(crate) fn is_loading(&self) -> bool{
+        return self.elements.iter().any(|node| node.loading)
+    }
+
+    ///Returns node ids with their display path (see `get_display_path`), in display order.
+    /// Nodes whose entry, or the entry of an ancestor, doesn't pass `include` are left out.
+    pub(crate) fn get_relative_paths(&self, include: impl Fn(&FileEntry) -> bool) -> Vec<(usize, String)>{
+        let order = self.get_order();
+        let mut paths = Vec::new();
+        let mut index = 0;
+
+        while index < order.len(){
+            let (id, subtree_len) = order[index];
+
+            match self.get_node(id){
+                Some(node) if include(&node.file_entry) => {
+                    paths.push((node.id, self.get_display_path(&node.file_entry.path).display().to_string()));
+                    index += 1;
+                }
+                _ => index += subtree_len
+            }
+        }
+
+        return paths
+    }
+
+    ///Queues every directory not read yet whose entry passes `include`, without expanding it, so that
+    /// lazy trees can be searched. Directories read meanwhile queue theirs on the next call.
+    /// Directories that failed to be read are left until expanded again.
+    pub(crate) fn load_unread(&mut self, include: impl Fn(&FileEntry) -> bool){
+        for node in self.elements.iter_mut(){
+            let unread = node.file_entry.is_dir && !node.loaded && !node.loading && node.file_entry.error.is_none();
+
+            if unread && include(&node.file_entry){
+                node.loading = true;
+                self.pending_loads.push((node.id, node.file_entry.path.clone()));
+            }
+        }
+    }
+
+    ///Takes the directories that were expanded but not yet read.
+    pub(crate) fn take_pending_loads(&mut self) -> Vec<(usize, PathBuf)>{
+        return std::mem::take(&mut self.pending_loads)
+    }
+
+    ///Takes the read failures collected since the last call.
+    pub(crate) fn take_diagnostics(&mut self) -> Vec<ReadError>{
+        return std::mem::take(&mut self.diagnostics)
+    }
+
+    ///Flags a directory whose contents couldn't be read, keeping the node in the tree.
+    fn mark_unreadable(&mut self, error: ReadError){
+        if let Some(node) = self.find_by_path(&error.path).and_then(|id| self.get_node_mut(id)){
+            node.file_entry.error = Some(error.message.clone());
+        }
+        self.diagnostics.push(error);
+    }
+
+    ///Adds a layer read outside of a `TreeBuilder`, e.g. in the background. Nodes whose parent
+    /// is missing by now are skipped, nodes of expanded parents are shown.
+    pub(crate) fn apply_layer(&mut self, layer: TreeLayer){
+        for error in layer.unreadable{
+            //Directories renamed or moved since the build started are read again under their new path, see `unload`
+            if self.find_by_path(&error.path).is_some(){
+                self.mark_unreadable(error);
+            }
+        }
+        self.diagnostics.extend(layer.errors);
+        self.build(&layer.entries);
+
+        for entry in &layer.entries{
+            let parent_path = entry.path.parent().unwrap_or(&entry.path);
+            let visible = self.find_by_path(parent_path)
+                .and_then(|parent_id| self.get_node(parent_id))
+                .is_some_and(|parent| parent.visible && parent.expanded);
+
+            if let Some(node) = self.find_by_path(&entry.path).and_then(|id| self.get_node_mut(id)){
+                node.visible = node.visible || visible;
+            }
+        }
+    }
+
+    ///Adds the contents of a lazily read directory as its children. Failed reads are kept as
+    /// errors of the directory, which can be expanded again to retry.
+    pub(crate) fn apply_loaded(&mut self, id: usize, directory: Result<DirectoryRead, EditorIoError>){
+        let (depth, visible, path) = match self.get_node_mut(id){
+            Some(node) => {
+                node.loading = false;
+                node.loaded = directory.is_ok();
+                node.file_entry.error = None;
+                (node.depth, node.visible && node.expanded, node.file_entry.path.clone())
+            },
+            None => return,
+        };
+        let directory = match directory{
+            Ok(directory) => directory,
+            Err(err) => {
+                self.mark_unreadable(ReadError::new(&path, &err));
+                return;
+            },
+        };
+        self.diagnostics.extend(directory.errors);
+
+        for element in directory.entries{
+            let child_id = self.ids.get_or_allocate(&element.path);
+
+            if self.lookup.contains_key(&child_id){
+                continue;
+            }
+            self.add_as_child(&element, id, depth, child_id);
+
+            if let Some(child) = self.get_node_mut(child_id){
+                child.visible = visible;
+                //Read on expansion, also in trees that aren't lazy (see `unload`)
+                child.loaded = !element.is_dir;
+            }
+            if let Some(node) = self.get_node_mut(id){
+                node.children.push(child_id);
+            }
+        }
+    }
+
+    ///Marks a directory and every directory below it as not read, e.g. when moved while their layers were being read
+    /// by their old path. Their contents are read again when expanded, expanded ones right away. Nodes already read are kept.
+    pub(crate) fn unload(&mut self, id: usize){
+        for child_id in self.get_subtree(id){
+            let Some(node) = self.get_node_mut(child_id).filter(|node| node.file_entry.is_dir) else{
+                continue;
+            };
+            node.loaded = false;
+
+            if node.expanded && !node.loading{
+                node.loading = true;
+                let load = (node.id, node.file_entry.path.clone());
+                self.pending_loads.push(load);
+            }
+        }
+    }
+
+    ///Expands a directory, showing its children. Does nothing if already expanded.
+    pub(crate) fn expand(&mut self, id: usize){
+        if self.get_node(id).is_some_and(|node| node.visible && !node.expanded){
+            self.toggle_visibility(&id);
+        }
+    }
+
     //Toggle or force expanded on children.
     fn toggle_expantion(&mut self, children: &Vec<usize>, force_expantion: Option<bool>){
         for child_id in children {
@@ -299,112 +819,443 @@ impl FlatTree{
                 node.expanded = expanded;
             }
         }
-    }
+    }
+
+    ///Toggle or force visibility on children.
+    fn toggle_children(&mut self, children: &Vec<usize>, force_visibility: Option<bool>) {
+        for child_id in children {
+            if let Some(node) = self.elements.iter_mut().find(|entry| entry.id == *child_id) {
+                let visibility = force_visibility.unwrap_or(!node.visible);
+                node.visible = visibility;
+
+                if !visibility {
+                    let grandchildren = node.children.clone();
+                    self.toggle_children(&grandchildren, Some(false));
+                }
+            }
+        }
+    }
+
+    ///Create a sub-section of FlatTree from nodes.
+    /// Used to retrieve the 'visible' UIDirectory tree.
+    pub(crate) fn get_children_from_ids(&self, children: &[usize]) -> Vec<&TreeNode>{
+        let mut returned = Vec::new();
+
+        for child_id in children{
+            if let Some(child) = self.get_node(*child_id){
+                returned.push(child);
+            }
+        }
+
+        return returned;
+    }
+
+    ///Removes a node and its whole subtree based on ID.
+    /// Returns the removed nodes, where the first element is the node itself.
+    pub(crate) fn remove(&mut self, id: usize) -> Option<Vec<TreeNode>>{
+        let parent_id = self.get_node(id)?.parent;
+        let subtree: HashSet<usize> = self.get_subtree(id).into_iter().collect();
+
+        let mut removed = Vec::new();
+        let mut kept = Vec::new();
+
+        for node in self.elements.drain(..){
+            if subtree.contains(&node.id){
+                removed.push(node);
+            }
+            else{
+                kept.push(node);
+            }
+        }
+        self.elements = kept;
+        //Keep the removed node as the first element
+        if let Some(pos) = removed.iter().position(|node| node.id == id){
+            removed.swap(0, pos);
+        }
+
+        self.rebuild_index();
+
+        if let Some(parent) = self.get_node_mut(parent_id){
+            parent.children.retain(|child| *child != id);
+        }
+
+        return Some(removed)
+    }
+
+    ///Collects a node and all of its descendants ids, parents before children.
+    fn get_subtree(&self, id: usize) -> Vec<usize>{
+        let mut subtree = Vec::new();
+        let mut stack = vec![id];
+
+        while let Some(current) = stack.pop(){
+            if let Some(node) = self.get_node(current){
+                stack.extend(node.children.iter().copied());
+                subtree.push(current);
+            }
+        }
+
+        return subtree
+    }
+
+    ///Returns the parent id of a node, or None if the node is a root node.
+    pub(crate) fn get_parent_id(&self, id: usize) -> Option<usize>{
+        return self.get_node(id)
+            .filter(|node| node.depth != 0)
+            .map(|node| node.parent)
+    }
+
+    ///Moves a node and its subtree under a new parent directory, or to root if `new_parent` is None.
+    /// Depth, parent and paths of the whole subtree are recomputed, ids are kept.
+    /// Returns the id of the moved node, None if the target is invalid or already has a node of the same name.
+    pub(crate) fn move_node(&mut self, id: usize, new_parent: Option<usize>) -> Option<usize>{
+        let subtree = self.get_subtree(id);
+        let node = self.get_node(id)?;
+        let old_path = node.file_entry.path.clone();
+        let old_depth = node.depth;
+        let old_parent = node.parent;
+
+        let (parent_id, parent_path, depth, visible) = match new_parent{
+            Some(parent_id) => {
+                let parent = self.get_node(parent_id)?;
+                //Can't move into a file or into own subtree
+                if !parent.file_entry.is_dir || subtree.contains(&parent_id){
+                    return None
+                }
+                (parent_id, parent.file_entry.path.clone(), parent.depth + 1, parent.visible && parent.expanded)
+            },
+            //Workspaces have no top-level directory
+            None => (0, self.get_top_level()?.to_path_buf(), 0, true),
+        };
+        let new_path = parent_path.join(old_path.file_name()?);
+        //Target name taken by another node
+        if self.find_by_path(&new_path).is_some(){
+            return None
+        }
+
+        //Unlink from old parent
+        if let Some(parent) = self.get_node_mut(old_parent){
+            parent.children.retain(|child| *child != id);
+        }
+
+        for node_id in &subtree{
+            if let Some(node) = self.get_node_mut(*node_id){
+                let relative = node.file_entry.path.strip_prefix(&old_path).map(|relative| relative.to_path_buf()).unwrap_or_default();
+                node.file_entry.path = if relative.as_os_str().is_empty(){ new_path.clone() } else { new_path.join(relative) };
+                node.depth = node.depth - old_depth + depth;
+
+                if *node_id == id{
+                    node.parent = parent_id;
+                    node.visible = visible;
+                    node.expanded = node.expanded && visible;
+                }
+
+                if let Some(parent_name) = node.file_entry.path.parent().and_then(|parent| parent.file_name()){
+                    node.file_entry.parent = parent_name.to_string_lossy().to_string();
+                }
+            }
+        }
+        self.rename_ids(&old_path, &new_path);
+        self.invalidate_order();
+
+        //Link to new parent
+        if let Some(parent) = self.get_node_mut(parent_id){
+            parent.children.push(id);
+        }
+        //Hide moved subtree if not visible anymore
+        if !visible{
+            let children = self.get_node(id).map(|node| node.children.clone()).unwrap_or_default();
+            self.toggle_children(&children, Some(false));
+            self.toggle_expantion(&children, Some(false));
+        }
+
+        return Some(id)
+    }
+
+    ///Re-inserts nodes previously returned by `remove`, and links them back to their parent.
+    pub(crate) fn restore(&mut self, nodes: Vec<TreeNode>){
+        let root_id = match nodes.first(){
+            Some(node) => node.id,
+            None => return,
+        };
+        let parent_id = nodes[0].parent;
+
+        //Paths may have been pruned or taken by a renamed entry in the meantime
+        for node in &nodes{
+            self.ids.assign(&node.file_entry.path, node.id);
+        }
+        self.elements.extend(nodes);
+        self.rebuild_index();
+
+        if let Some(parent) = self.get_node_mut(parent_id){
+            if !parent.children.contains(&root_id){
+                parent.children.push(root_id);
+            }
+        }
+    }
+
+    ///Returns the path of a node, if exists.
+    pub(crate) fn get_path(&self, id: usize) -> Option<PathBuf>{
+        return self.get_node(id).map(|node| node.file_entry.path.clone())
+    }
+
+    ///Path an id was allocated for, also known for removed nodes and nodes not read yet.
+    pub(crate) fn get_known_path(&self, id: usize) -> Option<PathBuf>{
+        return self.get_path(id).or_else(|| self.ids.get_path(id).map(|path| path.to_path_buf()))
+    }
+
+    ///Id of a path, allocated now if the path wasn't seen yet. The node takes it once read.
+    pub(crate) fn get_or_allocate_id(&mut self, path: &Path) -> usize{
+        return self.ids.get_or_allocate(path)
+    }
+
+    ///Renames a node, also changes its path, and if directory changes its descendants paths.
+    /// Returns the old name of the node.
+    pub(crate) fn rename(&mut self, id: usize, new_name: &String) -> Option<String>{
+        let node = self.get_node_mut(id)?;
+
+        let old_path = node.file_entry.path.clone();
+        let new_path = old_path.with_file_name(new_name);
+        let old_name = std::mem::replace(&mut node.file_entry.name, new_name.to_string());
+        node.file_entry.path = new_path.clone();
+
+        //If node is dir, change descendants paths.
+        if node.file_entry.is_dir{
+            let children = node.children.clone();
+            self.rename_parent(&children, &old_path, &new_path);
+        }
+        self.rename_ids(&old_path, &new_path);
+        //Sibling order depends on names
+        self.invalidate_order();
+
+        return Some(old_name)
+    }
+
+    ///Validates a new entry name under a parent directory (`None` is the tree root).
+    /// Checks the platform naming rules and collisions with siblings, other than `ignored_id`.
+    pub(crate) fn validate_name(&self, parent_id: Option<usize>, name: &str, ignored_id: Option<usize>) -> Result<(), EditorIoError>{
+        io::validate_name(name)
+            .map_err(|err| EditorIoError::new(&err.to_string(), ErrorType::InvalidName(err)))?;
+
+        let parent_path = match parent_id{
+            Some(parent_id) => self.get_path(parent_id).ok_or(EditorIoError::new("Node not found", ErrorType::NotFound))?,
+            None => self.get_top_level()
+                .map(|root| root.to_path_buf())
+                .ok_or(EditorIoError::new("Not a directory of the workspace", ErrorType::InvalidInput))?,
+        };
+
+        let collision = self.elements
+            .iter()
+            .filter(|node| Some(node.id) != ignored_id && node.file_entry.path.parent() == Some(parent_path.as_path()))
+            .find(|node| io::names_collide(&node.file_entry.name, name));
+
+        if let Some(sibling) = collision{
+            let err = NameError::Collision(sibling.file_entry.name.clone());
+            return Err(EditorIoError::new(&err.to_string(), ErrorType::InvalidName(err)))
+        }
+
+        return Ok(())
+    }
+
+    ///Renames the path of children whose ancestor has been renamed.
+    fn rename_parent(&mut self, children: &Vec<usize>, old_parent_path: &Path, new_parent_path: &Path){
+        for child in children{
+            let mut grandchildren = Vec::new();
+
+            if let Some(node) = self.get_node_mut(*child){
+                if let Ok(relative) = node.file_entry.path.strip_prefix(old_parent_path){
+                    node.file_entry.path = new_parent_path.join(relative);
+                }
+                if node.file_entry.path.parent() == Some(new_parent_path){
+                    if let Some(parent_name) = new_parent_path.file_name(){
+                        node.file_entry.parent = parent_name.to_string_lossy().to_string();
+                    }
+                }
+                grandchildren = node.children.clone();
+            }
+
+            self.rename_parent(&grandchildren, old_parent_path, new_parent_path);
+        }
+    }
+
+    ///Returns the id of the node located at `path`, if exists.
+    pub(crate) fn find_by_path(&self, path: &Path) -> Option<usize>{
+        return self.ids.get(path).filter(|id| self.contains(*id))
+    }
+
+    ///Inserts an entry that exists on disk under its parent node. Directories are read recursively, unless the tree is lazy.
+    /// Returns false if the entry already exists or its parent is not part of the tree.
+    pub(crate) fn insert_path(&mut self, path: &Path) -> Result<bool, EditorIoError>{
+        if self.find_by_path(path).is_some(){
+            return Ok(false)
+        }
+
+        let parent_path = match path.parent(){
+            Some(parent) => parent.to_path_buf(),
+            None => return Ok(false),
+        };
+        let entry = io::read_entry(path, self.get_rules(path))?;
+        let id = self.ids.get_or_allocate(&entry.path);
+
+        if self.get_top_level() == Some(parent_path.as_path()){
+            self.add_as_root(&entry, id);
+        }
+        else if let Some((_, parent_id, parent_depth)) = self.get_parent_from_path(&parent_path){
+            self.add_as_child(&entry, parent_id, parent_depth, id);
+
+            if let Some(parent) = self.get_node_mut(parent_id){
+                if !parent.children.contains(&id){
+                    parent.children.push(id);
+                }
+                //Show new node if parent is expanded
+                let visible = parent.visible && parent.expanded;
+                if let Some(node) = self.get_node_mut(id){
+                    node.visible = visible;
+                }
+            }
+        }
+        else{
+            return Ok(false)
+        }
 
-    ///Toggle or force visibility on children.
-    fn toggle_children(&mut self, children: &Vec<usize>, force_visibility: Option<bool>) {
-        for child_id in children {
-            if let Some(node) = self.elements.iter_mut().find(|entry| entry.id == *child_id) {
-                let visibility = force_visibility.unwrap_or(!node.visible);
-                node.visible = visibility;
+        if entry.is_dir && !self.lazy{
+            let directory = io::read_directory(path, self.get_rules(path))?;
+            self.diagnostics.extend(directory.errors);
 
-                if !visibility {
-                    let grandchildren = node.children.clone();
-                    self.toggle_children(&grandchildren, Some(false));
-                }
+            for child in directory.entries{
+                self.insert_path(&child.path)?;
             }
         }
+
+        return Ok(true)
     }
 
-    ///Create a sub-section of FlatTree from nodes.
-    /// Used to retrieve the 'visible' UIDirectory tree.
-    pub(crate) fn get_children_from_ids(&mut self, children: &Vec<usize>) -> Vec<&TreeNode>{
-        let mut returned = Vec::new();
+    ///Applies a change of an ignore file (`.gitignore`, `.ignore`), marking the entries of its directory
+    /// as ignored again. Returns false if `path` is not an ignore file.
+    pub(crate) fn apply_ignore_change(&mut self, path: &Path) -> bool{
+        if !self.get_rules(path).invalidate(path){
+            return false
+        }
+        let directory = path.parent().unwrap_or(path).to_path_buf();
 
-        for child_id in children{
-            if let Some(child) = self.elements.iter().find(|entry| entry.id == *child_id){
-                returned.push(child);
-            }
+        let rules = self.get_rules(&directory).clone();
+        for node in self.elements.iter_mut().filter(|node| node.file_entry.path.starts_with(&directory)){
+            node.file_entry.is_ignored = rules.is_ignored(&node.file_entry.path, node.file_entry.is_dir);
         }
+        self.dirty = true;
 
-        return returned;
+        return true
     }
 
-    ///Takes a node's path and returns a hashed id.
-    fn path_to_id(path: &PathBuf) -> usize{
-        let mut hasher = DefaultHasher::new();
-        let canonical_path = path.canonicalize().unwrap_or_else(|_| path.clone());
-        canonical_path.hash(&mut hasher);
+    ///Removes the node located at `path` and its subtree, if exists.
+    pub(crate) fn remove_path(&mut self, path: &Path) -> Option<Vec<TreeNode>>{
+        let id = self.find_by_path(path)?;
 
-        return hasher.finish() as usize
+        return self.remove(id)
     }
 
-    ///Removes a node based on ID.
-    pub(crate) fn remove(&mut self, id: usize) -> Option<TreeNode>{
-        if let Some(node_pos) = self.lookup.get(&id){
-            let node = self.elements.remove(*node_pos);
+    ///Applies a rename that happened on disk. Renames inside the same directory keep the node, 
+    /// otherwise the node is removed and re-inserted under its new parent.
+    pub(crate) fn rename_path(&mut self, from: &Path, to: &Path) -> Result<bool, EditorIoError>{
+        let id = match self.find_by_path(from){
+            Some(id) => id,
+            None => return self.insert_path(to),
+        };
 
-            if node.file_entry.is_dir{
-                for child in &node.children{
-                    self.remove(*child);
-                }
-            }
-            self.rebuild_index();
+        //Entry replaced on disk by the renamed one
+        if let Some(replaced_id) = self.find_by_path(to).filter(|replaced_id| *replaced_id != id){
+            self.remove(replaced_id);
+        }
 
-            return Some(node)
+        if from.parent() == to.parent(){
+            if let Some(new_name) = to.file_name(){
+                self.rename(id, &new_name.to_string_lossy().to_string());
+                return Ok(true)
+            }
         }
 
-        return None
+        //Re-inserted node keeps its id
+        self.remove(id);
+        self.rename_ids(from, to);
+        return self.insert_path(to)
     }
 
-    ///Renames a node, also changes its path, and if directory changes children parent path and their path.
-    pub(crate) fn rename(&mut self, id: usize, new_name: &String) -> Option<String>{
-        let mut nodes_to_change = Vec::new();
-        let mut new_parent_path = None;
-        let mut old_name = None;
-
-        if let Some(node_pos) = self.lookup.get(&id){
-            if let Some(node) = self.elements.get_mut(*node_pos){
-                //Change node's name and path
-                let new_path = Path::new(&node.file_entry.parent).join(&new_name);
-                old_name = Some(node.file_entry.path.display().to_string());
-                node.file_entry.name = new_name.to_string();
-                node.file_entry.path = new_path.clone();
-
-                if node.file_entry.is_dir{
-                    for child in node.children.clone(){
-                        nodes_to_change.push(child);
-                    }
-                    new_parent_path = Some(new_path);
-                }
-            }
-        }
+    ///Reads the metadata of a changed entry again. Returns the id of its node, if in the tree.
+    pub(crate) fn refresh_entry(&mut self, path: &Path) -> Result<Option<usize>, EditorIoError>{
+        let id = match self.find_by_path(path){
+            Some(id) => id,
+            None => return Ok(None),
+        };
+        let entry = io::read_entry(path, self.get_rules(path))?;
 
-        //If node is dir, change children parent and path.
-        if let Some(path) = new_parent_path{
-            for child in nodes_to_change{
-                self.rename_parent(child, &path);
-            }
+        if let Some(node) = self.get_node_mut(id){
+            node.file_entry = entry;
+        }
+        if self.sort_mode.uses_metadata(){
+            self.invalidate_order();
         }
 
-        return old_name
+        return Ok(Some(id))
     }
 
-    ///Renames a child component who's parent has been renamed.
-    fn rename_parent(&mut self, id: usize, new_parent_path: &PathBuf){
-        if let Some(node_pos) = self.lookup.get(&id){
-            if let Some(node) = self.elements.get_mut(*node_pos){
-                let new_path = Path::new(&new_parent_path).join(&node.file_entry.name);
-                //Assign new child path and parent path
-                node.file_entry.path = new_path.clone();
-                node.file_entry.parent = new_parent_path.display().to_string();
+    ///Moves the ids of a renamed path and its descendants. Ids of removed entries at the new path
+    /// are released, nodes still in the tree losing their path are reported.
+    fn rename_ids(&mut self, from: &Path, to: &Path){
+        for id in self.ids.rename(from, to){
+            if self.contains(id){
+                eprintln!("Node {} lost its path, {} was renamed over it", id, to.display());
             }
         }
     }
+
+    ///Forgets the ids of paths without a node, unless `referenced` outside of the tree.
+    pub(crate) fn prune_ids(&mut self, referenced: &HashSet<usize>){
+        let lookup = &self.lookup;
+        self.ids.retain(|id| lookup.contains_key(&id) || referenced.contains(&id));
+    }
+}
+
+
+
+
+///Contents of every directory of one depth, read together when building a tree.
+#[derive(Debug, Default)]
+pub(crate) struct TreeLayer{
+    entries: Vec<FileEntry>,
+    //Directories that failed to be read
+    unreadable: Vec<ReadError>,
+    //Entries that failed to be read
+    errors: Vec<ReadError>
 }
+impl TreeLayer{
+    ///Reads every directory of `directories`. Failures are collected and never abort the read.
+    pub(crate) fn read(directories: &[FileEntry], rules: &EntryRules) -> TreeLayer{
+        let mut layer = TreeLayer::default();
+
+        for directory in directories.iter().filter(|entry| entry.is_dir){
+            match io::read_directory(&directory.path, rules){
+                Ok(read) => {
+                    layer.entries.extend(read.entries);
+                    layer.errors.extend(read.errors);
+                },
+                Err(err) => layer.unreadable.push(ReadError::new(&directory.path, &err)),
+            }
+        }
 
+        return layer
+    }
 
+    ///Directories of the layer, to be read for the next one.
+    pub(crate) fn get_directories(&self) -> Vec<FileEntry>{
+        return self.entries.iter().filter(|entry| entry.is_dir).cloned().collect()
+    }
+}
 
+///Directories of a folder left to be read after its first layer, see `TreeBuilder::build_workspace`.
+#[derive(Debug, Clone)]
+pub(crate) struct PendingLayers{
+    pub(crate) directories: Vec<FileEntry>,
+    pub(crate) rules: EntryRules
+}
 
 ///Helper struct to build FlatTree structure layer by layer. 
 /// Helper contains `current` and `next` layers for building.
@@ -415,7 +1266,7 @@ pub(crate) struct TreeBuilder{
     tree: FlatTree,
 }
 impl TreeBuilder{
-    pub(crate) fn init(path: Option<PathBuf>) -> Result<TreeBuilder, EditorIoError>{
+    pub(crate) fn init(path: Option<PathBuf>, settings: &EditorSettings) -> Result<TreeBuilder, EditorIoError>{
         let editor_dir_path = {
             //If path provided
             if path.is_some(){
@@ -427,12 +1278,22 @@ impl TreeBuilder{
                 path.unwrap_or(&Arc::new(PathBuf::from(env::current_dir().unwrap()))).to_path_buf()
             }
         };
+        let rules = EntryRules::new(
+            &editor_dir_path, 
+            &settings.hidden_patterns, 
+            &settings.exclude_patterns,
+            settings.symlink_policy
+        );
         let current_directory = io::read_directory(
-            editor_dir_path.as_path()
+            editor_dir_path.as_path(),
+            &rules
         );
+        let mut tree = FlatTree::new(editor_dir_path, rules);
         
         match current_directory{
-            Ok(dir) => {
+            Ok(directory) => {
+                tree.diagnostics = directory.errors;
+                let dir = directory.entries;
                 let nested_dirs = dir
                     .iter()
                     .filter(|entry| entry.is_dir)
@@ -442,13 +1303,19 @@ impl TreeBuilder{
                 return Ok(TreeBuilder { 
                     current: Some(dir), 
                     next: Some(nested_dirs),
-                    tree: FlatTree::new()
+                    tree: tree
                 })
             },
             Err(err) => return Err(err.into()),
         }
     }
 
+    ///Reuses the ids of a previous tree, so that rebuilt nodes keep their identity.
+    pub(crate) fn with_ids(mut self, ids: NodeIds) -> TreeBuilder{
+        self.tree.ids = ids;
+        return self
+    }
+
     ///Build tree layer from current items.
     fn build_tree_layer(&mut self) -> bool{
         if let Some(current_directory) = &self.current.take(){
@@ -472,22 +1339,17 @@ impl TreeBuilder{
 
         //Get next targets (all dirs)
         if let Some(next_directory) = self.next.take(){
-            //For item in dir
-            for next_item in next_directory {
-                if next_item.is_dir{
-                    let directory = io::read_directory(
-                        &next_item.path
-                    )?;
-                    
-                    for entry in directory {
-                        // Collect subdirectories for the next level
-                        if entry.is_dir {
-                            dirs_for_next_level.push(entry.clone());
-                        }
-                        next_items.push(entry.clone());
-                    }
-                }
+            let layer = TreeLayer::read(&next_directory, self.tree.get_rules(self.tree.get_root()));
+
+            //Unreadable directories are flagged and skipped, the rest of the tree is still built
+            for error in layer.unreadable{
+                self.tree.mark_unreadable(error);
             }
+            self.tree.diagnostics.extend(layer.errors);
+
+            // Collect subdirectories for the next level
+            dirs_for_next_level = layer.entries.iter().filter(|entry| entry.is_dir).cloned().collect();
+            next_items = layer.entries;
         }
         
         // Set up for next iteration
@@ -523,6 +1385,83 @@ impl TreeBuilder{
         return Ok(())
     }
 
+    ///Builds only the first layer of the FlatTree, returning the directories left to read
+    /// so that they can be read elsewhere, e.g. in the background.
+    pub(crate) fn build_first_layer(&mut self) -> Option<PendingLayers>{
+        self.build_tree_layer();
+
+        return self.take_pending()
+    }
+
+    ///Takes the directories left to be read after the built layers.
+    fn take_pending(&mut self) -> Option<PendingLayers>{
+        let directories = self.next.take()?;
+
+        return Some(PendingLayers {
+            directories: directories,
+            rules: self.tree.get_rules(self.tree.get_root()).clone()
+        })
+    }
+
+    ///Builds only the first layer of the FlatTree. Directories are read when first expanded.
+    pub(crate) fn build_lazy(&mut self){
+        self.tree.lazy = true;
+        self.build_tree_layer();
+    }
+
+    ///Builds the project tree and mounts every folder of `roots` next to it, each read by its own
+    /// builder with its own rules. Paths known by `ids` keep their ids.
+    /// With `background`, only the first layer of each folder is read, the rest is returned to be read elsewhere.
+    pub(crate) fn build_workspace(settings: &EditorSettings, roots: &[PathBuf], ids: NodeIds, background: bool) -> Result<(FlatTree, Vec<PendingLayers>), EditorIoError>{
+        let (mut workspace, pending) = Self::build_root(None, settings, ids, background)?;
+        let mut pending: Vec<PendingLayers> = pending.into_iter().collect();
+
+        for root in roots{
+            match Self::mount_root(&mut workspace, root, settings, background){
+                Ok(root_pending) => pending.extend(root_pending),
+                Err(err) => eprintln!("Failed reading workspace folder {}: {}", root.display(), err),
+            }
+        }
+
+        return Ok((workspace, pending))
+    }
+
+    ///Builds a folder and mounts it in `workspace`. Relative folders are resolved against the project root,
+    /// see `io::resolve_path`. Folders overlapping a mounted one are rejected.
+    pub(crate) fn mount_root(workspace: &mut FlatTree, root: &Path, settings: &EditorSettings, background: bool) -> Result<Option<PendingLayers>, EditorIoError>{
+        let path = io::resolve_path(workspace.get_root(), root);
+
+        let overlaps = workspace.get_roots()
+            .into_iter()
+            .map(|existing| io::resolve_path(existing, Path::new("")))
+            .any(|existing| path.starts_with(&existing) || existing.starts_with(&path));
+        if overlaps{
+            return Err(EditorIoError::new("Folder overlaps another workspace folder", ErrorType::AlreadyExists))
+        }
+        let (tree, pending) = Self::build_root(Some(path), settings, workspace.get_ids().clone(), background)?;
+        workspace.mount(tree)?;
+
+        return Ok(pending)
+    }
+
+    ///Builds the tree of a single folder, lazily if enabled in `settings`. With `background`,
+    /// only the first layer is built and the directories left to read are returned.
+    fn build_root(path: Option<PathBuf>, settings: &EditorSettings, ids: NodeIds, background: bool) -> Result<(FlatTree, Option<PendingLayers>), EditorIoError>{
+        let mut builder = TreeBuilder::init(path, settings)?.with_ids(ids);
+
+        if settings.lazy_loading{
+            builder.build_lazy();
+            return Ok((builder.get_tree(), None))
+        }
+        if background{
+            let pending = builder.build_first_layer();
+            return Ok((builder.get_tree(), pending))
+        }
+
+        builder.build()?;
+        return Ok((builder.get_tree(), None))
+    }
+
     ///Retrieve Flat Tree.
     pub(crate) fn get_tree(&mut self) -> FlatTree{
         return self.tree.clone()
@@ -533,7 +1472,9 @@ impl TreeBuilder{
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::editor::objects::filter::FilterMode;
     use std::fs;
+    use std::time::{Duration, SystemTime};
 
     /* 
         Creates a directory and validates
@@ -573,7 +1514,7 @@ mod tests {
         fs::write(&sub_sub_file_1, "test1").unwrap();
         fs::write(&sub_sub_file_2, "test2").unwrap();
 
-        let mut builder = TreeBuilder::init(Some(PathBuf::from(test_dir))).unwrap();
+        let mut builder = TreeBuilder::init(Some(PathBuf::from(test_dir)), &EditorSettings::default()).unwrap();
         let _ = builder.build();
 
         let tree = builder.get_tree();
@@ -617,7 +1558,7 @@ mod tests {
     */
     #[test]
     fn test_node_duplicate_children() {
-        let mut builder = TreeBuilder::init(Some(PathBuf::from("."))).unwrap();
+        let mut builder = TreeBuilder::init(Some(PathBuf::from(".")), &EditorSettings::default()).unwrap();
         let _ = builder.build();
         let tree = builder.get_tree();
 
@@ -637,7 +1578,7 @@ mod tests {
     */
     #[test]
     fn test_node_duplicates() {
-        let mut builder = TreeBuilder::init(Some(PathBuf::from("."))).unwrap();
+        let mut builder = TreeBuilder::init(Some(PathBuf::from(".")), &EditorSettings::default()).unwrap();
         let _ = builder.build();
         let tree = builder.get_tree();
         let tree_items = tree.elements.clone();
@@ -654,4 +1595,729 @@ mod tests {
         }
     }
 
-}
\ No newline at end of file
+
+    /* 
+        Renames a directory and validates that descendants paths follow it,
+        then removes it and validates the whole subtree can be restored.
+    */
+    #[test]
+    fn test_rename_remove_restore() {
+        let test_dir = env::temp_dir().join("reasy_test_rename_remove");
+        let sub_sub_dir = test_dir.join("sub").join("sub");
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(&sub_sub_dir).unwrap();
+        fs::write(sub_sub_dir.join("file1.txt"), "test1").unwrap();
+
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &EditorSettings::default()).unwrap();
+        let _ = builder.build();
+        let mut tree = builder.get_tree();
+
+        let sub_id = tree.find_by_path(&test_dir.join("sub")).unwrap();
+        let file_id = tree.find_by_path(&sub_sub_dir.join("file1.txt")).unwrap();
+
+        //Rename
+        let old_name = tree.rename(sub_id, &"renamed".to_string());
+        assert_eq!(old_name, Some("sub".to_string()));
+        assert_eq!(tree.get_path(file_id), Some(test_dir.join("renamed").join("sub").join("file1.txt")));
+
+        //Remove
+        let removed = tree.remove(sub_id).unwrap();
+        assert_eq!(removed[0].id, sub_id);
+        assert_eq!(removed.len(), 3);
+        assert!(tree.elements.is_empty());
+
+        //Restore
+        tree.restore(removed);
+        assert_eq!(tree.elements.len(), 3);
+        assert!(tree.get_node(file_id).is_some());
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+
+    /* 
+        Applies filesystem changes as deltas and validates the tree follows the disk
+        without being rebuilt.
+    */
+    #[test]
+    fn test_path_deltas() {
+        let test_dir = env::temp_dir().join("reasy_test_path_deltas");
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(test_dir.join("sub")).unwrap();
+
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &EditorSettings::default()).unwrap();
+        let _ = builder.build();
+        let mut tree = builder.get_tree();
+        assert_eq!(tree.elements.len(), 1);
+
+        //Insert directory with content
+        let new_dir = test_dir.join("sub").join("new");
+        fs::create_dir_all(&new_dir).unwrap();
+        fs::write(new_dir.join("file1.txt"), "test1").unwrap();
+        assert!(tree.insert_path(&new_dir).unwrap());
+        assert!(!tree.insert_path(&new_dir).unwrap());
+        assert_eq!(tree.elements.len(), 3);
+
+        let new_dir_id = tree.find_by_path(&new_dir).unwrap();
+        assert_eq!(tree.get_node(new_dir_id).unwrap().depth, 1);
+
+        //Rename in place
+        let renamed_dir = test_dir.join("sub").join("renamed");
+        fs::rename(&new_dir, &renamed_dir).unwrap();
+        assert!(tree.rename_path(&new_dir, &renamed_dir).unwrap());
+        assert_eq!(tree.find_by_path(&renamed_dir), Some(new_dir_id));
+        assert!(tree.find_by_path(&renamed_dir.join("file1.txt")).is_some());
+
+        //Move to root
+        let moved_dir = test_dir.join("moved");
+        fs::rename(&renamed_dir, &moved_dir).unwrap();
+        assert!(tree.rename_path(&renamed_dir, &moved_dir).unwrap());
+        let moved_id = tree.find_by_path(&moved_dir).unwrap();
+        assert_eq!(tree.get_node(moved_id).unwrap().depth, 0);
+        assert_eq!(tree.elements.len(), 3);
+
+        //Remove
+        fs::remove_dir_all(&moved_dir).unwrap();
+        assert_eq!(tree.remove_path(&moved_dir).map(|nodes| nodes.len()), Some(2));
+        assert_eq!(tree.elements.len(), 1);
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+
+    /* 
+        Moves a directory under another directory and validates depth, parent,
+        children and lookup of the whole moved subtree, whose ids are kept.
+    */
+    #[test]
+    fn test_move_node() {
+        let test_dir = env::temp_dir().join("reasy_test_move_node");
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(test_dir.join("a").join("nested")).unwrap();
+        fs::create_dir_all(test_dir.join("b")).unwrap();
+        fs::write(test_dir.join("a").join("nested").join("file1.txt"), "test1").unwrap();
+        fs::write(test_dir.join("a").join("file1.txt"), "other").unwrap();
+
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &EditorSettings::default()).unwrap();
+        let _ = builder.build();
+        let mut tree = builder.get_tree();
+
+        let a_id = tree.find_by_path(&test_dir.join("a")).unwrap();
+        let b_id = tree.find_by_path(&test_dir.join("b")).unwrap();
+        let other_id = tree.find_by_path(&test_dir.join("a").join("file1.txt")).unwrap();
+
+        //Can't move into own subtree
+        let nested_id = tree.find_by_path(&test_dir.join("a").join("nested")).unwrap();
+        assert!(tree.move_node(a_id, Some(nested_id)).is_none());
+        //Can't move onto a node of the same name
+        let file_id = tree.find_by_path(&test_dir.join("a").join("nested").join("file1.txt")).unwrap();
+        assert!(tree.move_node(file_id, Some(a_id)).is_none());
+        assert_eq!(tree.find_by_path(&test_dir.join("a").join("file1.txt")), Some(other_id));
+
+        let new_id = tree.move_node(a_id, Some(b_id)).unwrap();
+        let moved_file = test_dir.join("b").join("a").join("nested").join("file1.txt");
+
+        assert_eq!(new_id, a_id);
+        assert_eq!(tree.find_by_path(&moved_file), Some(file_id));
+        assert_eq!(tree.find_by_path(&test_dir.join("a")), None);
+        assert_eq!(tree.get_node(file_id).unwrap().depth, 3);
+        assert_eq!(tree.get_parent_id(new_id), Some(b_id));
+        assert!(tree.get_node(b_id).unwrap().children.contains(&new_id));
+
+        //Move back to root
+        let root_id = tree.move_node(new_id, None).unwrap();
+        assert_eq!(root_id, a_id);
+        assert_eq!(tree.get_parent_id(root_id), None);
+        assert!(tree.get_node(b_id).unwrap().children.is_empty());
+        assert_eq!(tree.get_node(nested_id).unwrap().depth, 1);
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+
+    /* 
+        Builds a lazy tree and validates that directories are only read
+        once expanded.
+    */
+    #[test]
+    fn test_lazy_loading() {
+        let test_dir = env::temp_dir().join("reasy_test_lazy_loading");
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(test_dir.join("sub").join("sub")).unwrap();
+        fs::write(test_dir.join("sub").join("file1.txt"), "test1").unwrap();
+
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &EditorSettings::default()).unwrap();
+        builder.build_lazy();
+        let mut tree = builder.get_tree();
+        assert_eq!(tree.elements.len(), 1);
+
+        let sub_id = tree.find_by_path(&test_dir.join("sub")).unwrap();
+        tree.toggle_visibility(&sub_id);
+        assert!(tree.get_node(sub_id).unwrap().is_loading());
+
+        let pending = tree.take_pending_loads();
+        assert_eq!(pending.len(), 1);
+        assert!(tree.take_pending_loads().is_empty());
+
+        let (id, path) = pending[0].clone();
+        tree.apply_loaded(id, io::read_directory(&path, &EntryRules::default()));
+        assert!(!tree.get_node(sub_id).unwrap().is_loading());
+        assert_eq!(tree.elements.len(), 3);
+        assert_eq!(tree.get_visible_items().len(), 3);
+
+        //Reloading doesn't duplicate, collapsing and expanding doesn't read again
+        tree.apply_loaded(id, io::read_directory(&path, &EntryRules::default()));
+        assert_eq!(tree.elements.len(), 3);
+        tree.toggle_visibility(&sub_id);
+        tree.toggle_visibility(&sub_id);
+        assert!(tree.take_pending_loads().is_empty());
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+
+    /* 
+        Builds a tree with ignore files and editor excludes and validates
+        which entries are marked as ignored.
+    */
+    #[test]
+    fn test_ignore_rules() {
+        let test_dir = env::temp_dir().join("reasy_test_ignore_rules");
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(test_dir.join(".git")).unwrap();
+        fs::create_dir_all(test_dir.join("target").join("debug")).unwrap();
+        fs::create_dir_all(test_dir.join("logs")).unwrap();
+        fs::write(test_dir.join(".gitignore"), "target/\n*.log\n").unwrap();
+        fs::write(test_dir.join("logs").join(".ignore"), "!keep.log\n").unwrap();
+        fs::write(test_dir.join("logs").join("drop.log"), "").unwrap();
+        fs::write(test_dir.join("logs").join("keep.log"), "").unwrap();
+        fs::write(test_dir.join("main.rs"), "").unwrap();
+
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &EditorSettings::default()).unwrap();
+        let _ = builder.build();
+        let tree = builder.get_tree();
+
+        let is_ignored = |path: PathBuf| {
+            let id = tree.find_by_path(&path).unwrap();
+            tree.get_node(id).unwrap().file_entry.is_ignored
+        };
+
+        assert!(is_ignored(test_dir.join(".git")));
+        assert!(is_ignored(test_dir.join("target")));
+        assert!(is_ignored(test_dir.join("target").join("debug")));
+        assert!(is_ignored(test_dir.join("logs").join("drop.log")));
+        assert!(!is_ignored(test_dir.join("logs").join("keep.log")));
+        assert!(!is_ignored(test_dir.join("logs")));
+        assert!(!is_ignored(test_dir.join("main.rs")));
+
+        //Edited ignore files are read again
+        let mut tree = tree.clone();
+        fs::write(test_dir.join(".gitignore"), "*.rs\n").unwrap();
+        assert!(tree.apply_ignore_change(&test_dir.join(".gitignore")));
+        assert!(!tree.apply_ignore_change(&test_dir.join("main.rs")));
+        fs::remove_file(test_dir.join("logs").join(".ignore")).unwrap();
+        assert!(tree.apply_ignore_change(&test_dir.join("logs").join(".ignore")));
+
+        let is_ignored = |path: PathBuf| {
+            let id = tree.find_by_path(&path).unwrap();
+            tree.get_node(id).unwrap().file_entry.is_ignored
+        };
+        assert!(is_ignored(test_dir.join("main.rs")));
+        assert!(!is_ignored(test_dir.join("target")));
+        assert!(!is_ignored(test_dir.join("logs").join("drop.log")));
+        assert!(is_ignored(test_dir.join(".git")));
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+
+    /* 
+        Reveals a nested node and validates all of its ancestors are expanded.
+    */
+    #[test]
+    fn test_reveal() {
+        let test_dir = env::temp_dir().join("reasy_test_reveal");
+        let nested_dir = test_dir.join("a").join("b");
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(&nested_dir).unwrap();
+        fs::write(nested_dir.join("file1.txt"), "test1").unwrap();
+
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &EditorSettings::default()).unwrap();
+        let _ = builder.build();
+        let mut tree = builder.get_tree();
+        assert_eq!(tree.get_visible_items().len(), 1);
+
+        let file_id = tree.find_by_path(&nested_dir.join("file1.txt")).unwrap();
+        tree.reveal(file_id);
+
+        assert!(tree.get_visible_items().iter().any(|node| node.id == file_id));
+        let file_path = Path::new("a").join("b").join("file1.txt").display().to_string();
+        assert!(tree.get_relative_paths(|_| true).iter().any(|(_, path)| path == &file_path));
+        //Excluded directories exclude their whole subtree
+        assert!(tree.get_relative_paths(|entry| entry.name != "b").iter().all(|(_, path)| path != &file_path));
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+
+    /* 
+        Filters a tree and validates that matches are kept with their ancestors,
+        in display order, without touching the expansion state.
+    */
+    #[test]
+    fn test_filtered_items() {
+        let test_dir = env::temp_dir().join("reasy_test_filtered_items");
+        let nested_dir = test_dir.join("a").join("b");
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(&nested_dir).unwrap();
+        fs::create_dir_all(test_dir.join("c")).unwrap();
+        fs::write(nested_dir.join("shader.wgsl"), "").unwrap();
+        fs::write(test_dir.join("c").join("main.rs"), "").unwrap();
+        fs::write(test_dir.join("Shader.json"), "").unwrap();
+
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &EditorSettings::default()).unwrap();
+        let _ = builder.build();
+        let tree = builder.get_tree();
+
+        let mut filter = TreeFilter::default();
+        filter.query = "shader".to_string();
+        filter.update();
+
+        let names = |filter: &TreeFilter| tree.get_filtered_items(filter)
+            .iter()
+            .map(|node| node.file_entry.name.clone())
+            .collect::<Vec<String>>();
+
+        assert_eq!(names(&filter), vec!["a", "b", "shader.wgsl", "Shader.json"]);
+        assert!(tree.elements.iter().all(|node| !node.expanded));
+
+        filter.mode = FilterMode::Glob;
+        filter.query = "*.rs".to_string();
+        filter.update();
+        assert_eq!(names(&filter), vec!["c", "main.rs"]);
+
+        filter.mode = FilterMode::Regex;
+        filter.query = "^(main|shader)\\.".to_string();
+        filter.update();
+        assert_eq!(names(&filter), vec!["a", "b", "shader.wgsl", "c", "main.rs", "Shader.json"]);
+
+        filter.query = "(".to_string();
+        filter.update();
+        assert!(!filter.is_active());
+        assert!(filter.error.is_some());
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+
+
+    /* 
+        Expands and collapses directories and validates that visible items follow
+        the cached pre-order, skipping collapsed subtrees and picking up new nodes.
+    */
+    #[test]
+    fn test_visible_items_order() {
+        let test_dir = env::temp_dir().join("reasy_test_visible_items_order");
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(test_dir.join("a").join("y")).unwrap();
+        fs::create_dir_all(test_dir.join("b")).unwrap();
+        fs::write(test_dir.join("a").join("x.txt"), "").unwrap();
+        fs::write(test_dir.join("a").join("y").join("z.txt"), "").unwrap();
+        fs::write(test_dir.join("c.txt"), "").unwrap();
+
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &EditorSettings::default()).unwrap();
+        let _ = builder.build();
+        let mut tree = builder.get_tree();
+
+        let names = |tree: &FlatTree| tree.get_visible_items()
+            .iter()
+            .map(|node| node.file_entry.name.clone())
+            .collect::<Vec<String>>();
+
+        assert_eq!(names(&tree), vec!["a", "b", "c.txt"]);
+
+        let a_id = tree.find_by_path(&test_dir.join("a")).unwrap();
+        let y_id = tree.find_by_path(&test_dir.join("a").join("y")).unwrap();
+        tree.toggle_visibility(&a_id);
+        assert_eq!(names(&tree), vec!["a", "y", "x.txt", "b", "c.txt"]);
+
+        tree.toggle_visibility(&y_id);
+        assert_eq!(names(&tree), vec!["a", "y", "z.txt", "x.txt", "b", "c.txt"]);
+
+        //New nodes invalidate the cached order
+        fs::write(test_dir.join("a").join("w.txt"), "").unwrap();
+        assert!(tree.insert_path(&test_dir.join("a").join("w.txt")).unwrap());
+        assert_eq!(names(&tree), vec!["a", "y", "z.txt", "w.txt", "x.txt", "b", "c.txt"]);
+
+        tree.toggle_visibility(&a_id);
+        assert_eq!(names(&tree), vec!["a", "b", "c.txt"]);
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+
+    /* 
+        Switches sort modes and validates the order of visible items, with directories always first.
+        Refreshed metadata reorders the size and modification date modes.
+    */
+    #[test]
+    fn test_sort_modes() {
+        let test_dir = env::temp_dir().join("reasy_test_sort_modes");
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(test_dir.join("Dir")).unwrap();
+        fs::write(test_dir.join("file10.rs"), "1234567890").unwrap();
+        fs::write(test_dir.join("file2.txt"), "12345").unwrap();
+        fs::write(test_dir.join("B.md"), "").unwrap();
+
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &EditorSettings::default()).unwrap();
+        let _ = builder.build();
+        let mut tree = builder.get_tree();
+
+        let names = |tree: &mut FlatTree, mode: SortMode| {
+            tree.set_sort_mode(mode);
+            tree.get_visible_items()
+                .iter()
+                .map(|node| node.file_entry.name.clone())
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(names(&mut tree, SortMode::Name), vec!["Dir", "B.md", "file10.rs", "file2.txt"]);
+        assert_eq!(names(&mut tree, SortMode::Natural), vec!["Dir", "B.md", "file2.txt", "file10.rs"]);
+        assert_eq!(names(&mut tree, SortMode::CaseInsensitive), vec!["Dir", "B.md", "file10.rs", "file2.txt"]);
+        assert_eq!(names(&mut tree, SortMode::Extension), vec!["Dir", "B.md", "file10.rs", "file2.txt"]);
+        assert_eq!(names(&mut tree, SortMode::Size), vec!["Dir", "file10.rs", "file2.txt", "B.md"]);
+
+        let mut modified = |name: &str, seconds: u64| {
+            let file = fs::File::options().write(true).open(test_dir.join(name)).unwrap();
+            file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds)).unwrap();
+            tree.refresh_entry(&test_dir.join(name)).unwrap();
+        };
+        modified("B.md", 3000);
+        modified("file10.rs", 1000);
+        modified("file2.txt", 2000);
+        assert_eq!(names(&mut tree, SortMode::Modified), vec!["Dir", "B.md", "file2.txt", "file10.rs"]);
+
+        //Changed contents reorder metadata based modes
+        fs::write(test_dir.join("B.md"), "123456789012345").unwrap();
+        tree.take_dirty();
+        assert!(tree.refresh_entry(&test_dir.join("B.md")).unwrap().is_some());
+        assert!(tree.take_dirty());
+        assert_eq!(names(&mut tree, SortMode::Size), vec!["Dir", "B.md", "file10.rs", "file2.txt"]);
+
+        //Unchanged mode keeps the cached order
+        tree.take_dirty();
+        tree.set_sort_mode(SortMode::Size);
+        assert!(!tree.take_dirty());
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+
+    /* 
+        Renames and moves nodes, then rebuilds the tree from disk with the previous ids and validates
+            1. Ids survive renames, moves and disk renames (watcher deltas)
+            2. Rebuilt nodes keep the ids of the previous tree
+            3. New paths get new ids, removed paths get theirs back when re-inserted
+            4. Entries renamed over on disk release their id
+            5. Pruned ids are forgotten unless referenced
+    */
+    #[test]
+    fn test_stable_ids() {
+        let test_dir = env::temp_dir().join("reasy_test_stable_ids");
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(test_dir.join("a").join("nested")).unwrap();
+        fs::create_dir_all(test_dir.join("b")).unwrap();
+        fs::write(test_dir.join("a").join("nested").join("file1.txt"), "test1").unwrap();
+        fs::write(test_dir.join("file2.txt"), "test2").unwrap();
+
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &EditorSettings::default()).unwrap();
+        let _ = builder.build();
+        let mut tree = builder.get_tree();
+
+        let a_id = tree.find_by_path(&test_dir.join("a")).unwrap();
+        let b_id = tree.find_by_path(&test_dir.join("b")).unwrap();
+        let file1_id = tree.find_by_path(&test_dir.join("a").join("nested").join("file1.txt")).unwrap();
+        let file2_id = tree.find_by_path(&test_dir.join("file2.txt")).unwrap();
+
+        //Rename on disk and in the tree
+        fs::rename(test_dir.join("a"), test_dir.join("renamed")).unwrap();
+        tree.rename(a_id, &"renamed".to_string());
+        let renamed_file = test_dir.join("renamed").join("nested").join("file1.txt");
+        assert_eq!(tree.find_by_path(&renamed_file), Some(file1_id));
+
+        //Move on disk, applied as a watcher delta
+        fs::rename(test_dir.join("file2.txt"), test_dir.join("b").join("file2.txt")).unwrap();
+        tree.rename_path(&test_dir.join("file2.txt"), &test_dir.join("b").join("file2.txt")).unwrap();
+        assert_eq!(tree.find_by_path(&test_dir.join("b").join("file2.txt")), Some(file2_id));
+
+        //Rebuild
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &EditorSettings::default())
+            .unwrap()
+            .with_ids(tree.get_ids().clone());
+        let _ = builder.build();
+        let mut rebuilt = builder.get_tree();
+
+        assert_eq!(rebuilt.find_by_path(&test_dir.join("renamed")), Some(a_id));
+        assert_eq!(rebuilt.find_by_path(&test_dir.join("b")), Some(b_id));
+        assert_eq!(rebuilt.find_by_path(&renamed_file), Some(file1_id));
+        assert_eq!(rebuilt.find_by_path(&test_dir.join("b").join("file2.txt")), Some(file2_id));
+        assert_eq!(rebuilt.get_parent_id(file2_id), Some(b_id));
+
+        //New and re-inserted paths
+        fs::write(test_dir.join("file3.txt"), "test3").unwrap();
+        rebuilt.insert_path(&test_dir.join("file3.txt")).unwrap();
+        let file3_id = rebuilt.find_by_path(&test_dir.join("file3.txt")).unwrap();
+        assert!(![a_id, b_id, file1_id, file2_id].contains(&file3_id));
+
+        rebuilt.remove_path(&test_dir.join("file3.txt"));
+        assert!(rebuilt.find_by_path(&test_dir.join("file3.txt")).is_none());
+        rebuilt.insert_path(&test_dir.join("file3.txt")).unwrap();
+        assert_eq!(rebuilt.find_by_path(&test_dir.join("file3.txt")), Some(file3_id));
+
+        //Disk rename over an existing entry replaces its node and id
+        fs::write(test_dir.join("file4.txt"), "test4").unwrap();
+        rebuilt.insert_path(&test_dir.join("file4.txt")).unwrap();
+        let file4_id = rebuilt.find_by_path(&test_dir.join("file4.txt")).unwrap();
+        fs::rename(test_dir.join("file4.txt"), test_dir.join("file3.txt")).unwrap();
+        rebuilt.rename_path(&test_dir.join("file4.txt"), &test_dir.join("file3.txt")).unwrap();
+        assert_eq!(rebuilt.find_by_path(&test_dir.join("file3.txt")), Some(file4_id));
+        assert!(!rebuilt.contains(file3_id) && rebuilt.get_known_path(file3_id).is_none());
+
+        //Pruning forgets unreferenced ids of removed paths
+        rebuilt.remove_path(&test_dir.join("file3.txt"));
+        rebuilt.remove_path(&test_dir.join("b").join("file2.txt"));
+        rebuilt.prune_ids(&[file2_id].into_iter().collect());
+        assert!(rebuilt.get_known_path(file4_id).is_none());
+        assert_eq!(rebuilt.get_known_path(file2_id), Some(test_dir.join("b").join("file2.txt")));
+        rebuilt.insert_path(&test_dir.join("file3.txt")).unwrap();
+        assert!(rebuilt.find_by_path(&test_dir.join("file3.txt")).is_some_and(|id| id != file4_id));
+        rebuilt.insert_path(&test_dir.join("b").join("file2.txt")).unwrap();
+        assert_eq!(rebuilt.find_by_path(&test_dir.join("b").join("file2.txt")), Some(file2_id));
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+
+    /*
+        Validates that mounted roots appear as named top-level folders with their entries
+        one level deeper, keep unique ids, and support inserts and moves across roots.
+        Overlapping folders are rejected, and unmounting the last folder shows the project
+        entries at the top level again, even if the project folder was collapsed.
+    */
+    #[test]
+    fn test_workspace() {
+        let test_dir = env::temp_dir().join("reasy_test_workspace");
+        let _ = fs::remove_dir_all(&test_dir);
+        let project = test_dir.join("project");
+        let library = test_dir.join("library");
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::create_dir_all(library.join("assets")).unwrap();
+        fs::write(project.join("file1.txt"), "test1").unwrap();
+        fs::write(library.join("file2.txt"), "test2").unwrap();
+        fs::write(library.join(".gitignore"), "*.log").unwrap();
+
+        let mut builder = TreeBuilder::init(Some(project.clone()), &EditorSettings::default()).unwrap();
+        let _ = builder.build();
+        let mut tree = builder.get_tree();
+        assert_eq!(tree.get_top_level(), Some(project.as_path()));
+
+        let mut builder = TreeBuilder::init(Some(library.clone()), &EditorSettings::default())
+            .unwrap()
+            .with_ids(tree.get_ids().clone());
+        let _ = builder.build();
+        tree.mount(builder.get_tree()).unwrap();
+
+        assert!(tree.is_workspace());
+        assert_eq!(tree.get_top_level(), None);
+        assert_eq!(tree.get_roots(), vec![project.as_path(), library.as_path()]);
+
+        let project_id = tree.find_by_path(&project).unwrap();
+        let library_id = tree.find_by_path(&library).unwrap();
+        let file1_id = tree.find_by_path(&project.join("file1.txt")).unwrap();
+        let file2_id = tree.find_by_path(&library.join("file2.txt")).unwrap();
+        assert!(tree.is_workspace_root(project_id) && tree.is_workspace_root(library_id));
+        assert!(!tree.is_workspace_root(file1_id));
+        assert_eq!(tree.get_node(file2_id).unwrap().depth, 1);
+        assert_eq!(tree.get_parent_id(file2_id), Some(library_id));
+
+        let ids: HashSet<usize> = [project_id, library_id, file1_id, file2_id].into_iter().collect();
+        assert_eq!(ids.len(), 4);
+
+        assert_eq!(tree.get_display_path(&library.join("file2.txt")), PathBuf::from("library").join("file2.txt"));
+        //Each root has its own ignore rules
+        assert!(tree.get_rules(&library.join("file2.txt")).is_ignored(&library.join("debug.log"), false));
+        assert!(!tree.get_rules(&project.join("file1.txt")).is_ignored(&project.join("debug.log"), false));
+
+        //No top level outside of the roots
+        assert_eq!(tree.move_node(file1_id, None), None);
+
+        //Insert and move into the second root
+        fs::write(library.join("assets").join("file3.txt"), "test3").unwrap();
+        tree.insert_path(&library.join("assets").join("file3.txt")).unwrap();
+        let file3_id = tree.find_by_path(&library.join("assets").join("file3.txt")).unwrap();
+        assert_eq!(tree.get_node(file3_id).unwrap().depth, 2);
+
+        let assets_id = tree.find_by_path(&library.join("assets")).unwrap();
+        fs::rename(project.join("file1.txt"), library.join("assets").join("file1.txt")).unwrap();
+        assert_eq!(tree.move_node(file1_id, Some(assets_id)), Some(file1_id));
+        assert_eq!(tree.find_by_path(&library.join("assets").join("file1.txt")), Some(file1_id));
+        assert_eq!(tree.get_node(file1_id).unwrap().depth, 2);
+
+        //Overlapping folders
+        assert!(TreeBuilder::mount_root(&mut tree, &project.join("src"), &EditorSettings::default(), false).is_err());
+        assert!(TreeBuilder::mount_root(&mut tree, Path::new("./src"), &EditorSettings::default(), false).is_err());
+        assert!(TreeBuilder::mount_root(&mut tree, Path::new("../library/assets"), &EditorSettings::default(), false).is_err());
+        assert_eq!(tree.get_roots().len(), 2);
+
+        //Unmount
+        let src_id = tree.find_by_path(&project.join("src")).unwrap();
+        tree.toggle_visibility(&project_id);
+        assert!(!tree.unmount(&project.join("src")));
+        assert!(tree.unmount(&library));
+
+        assert!(!tree.is_workspace());
+        assert_eq!(tree.get_top_level(), Some(project.as_path()));
+        assert!(!tree.contains(library_id) && !tree.contains(file1_id) && !tree.contains(project_id));
+        assert_eq!(tree.get_parent_id(src_id), None);
+        assert_eq!(tree.get_node(src_id).unwrap().depth, 0);
+        assert_eq!(tree.get_visible_items().iter().map(|node| node.id).collect::<Vec<usize>>(), vec![src_id]);
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+
+    /*
+        Builds a directory with looping symlinks under every symlink policy and validates that
+        the build finishes, loops are detected (directly and through several links) and
+        broken links are reported.
+            a/loop -> a
+            b/to_c -> c, c/to_b -> b
+            broken -> missing
+    */
+    #[test]
+    #[cfg(unix)]
+    fn test_symlink_policies() {
+        use crate::utils::{io::LinkStatus, rules::SymlinkPolicy};
+        use std::os::unix::fs::symlink;
+
+        let test_dir = env::temp_dir().join("reasy_test_symlink_policies");
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(test_dir.join("a")).unwrap();
+        fs::create_dir_all(test_dir.join("b")).unwrap();
+        fs::create_dir_all(test_dir.join("c")).unwrap();
+        fs::write(test_dir.join("c").join("file1.txt"), "test1").unwrap();
+        symlink(test_dir.join("a"), test_dir.join("a").join("loop")).unwrap();
+        symlink(test_dir.join("c"), test_dir.join("b").join("to_c")).unwrap();
+        symlink(test_dir.join("b"), test_dir.join("c").join("to_b")).unwrap();
+        symlink(test_dir.join("missing"), test_dir.join("broken")).unwrap();
+
+        let build = |policy: SymlinkPolicy| {
+            let settings = EditorSettings { symlink_policy: policy, ..EditorSettings::default() };
+            let mut builder = TreeBuilder::init(Some(test_dir.clone()), &settings).unwrap();
+            builder.build().unwrap();
+            builder.get_tree()
+        };
+        let link = |tree: &FlatTree, path: PathBuf| {
+            let id = tree.find_by_path(&path).unwrap();
+            tree.get_node(id).unwrap().file_entry.link
+        };
+
+        //Links are leaves
+        let tree = build(SymlinkPolicy::DontFollow);
+        assert_eq!(link(&tree, test_dir.join("b").join("to_c")), Some(LinkStatus::NotFollowed));
+        assert!(tree.find_by_path(&test_dir.join("b").join("to_c").join("file1.txt")).is_none());
+        assert_eq!(link(&tree, test_dir.join("broken")), Some(LinkStatus::Broken));
+        assert_eq!(link(&tree, test_dir.join("c")), None);
+
+        //Links inside followed links are leaves
+        let tree = build(SymlinkPolicy::FollowOnce);
+        assert!(tree.find_by_path(&test_dir.join("b").join("to_c").join("file1.txt")).is_some());
+        assert_eq!(link(&tree, test_dir.join("b").join("to_c").join("to_b")), Some(LinkStatus::NotFollowed));
+        assert!(tree.find_by_path(&test_dir.join("a").join("loop").join("loop")).is_some());
+
+        //Links are followed until they lead back into a directory they are inside of
+        let tree = build(SymlinkPolicy::Follow);
+        assert_eq!(link(&tree, test_dir.join("a").join("loop")), Some(LinkStatus::Cycle));
+        assert!(tree.find_by_path(&test_dir.join("a").join("loop").join("loop")).is_none());
+        assert_eq!(link(&tree, test_dir.join("b").join("to_c")), Some(LinkStatus::Followed));
+        assert!(tree.find_by_path(&test_dir.join("b").join("to_c").join("file1.txt")).is_some());
+        assert_eq!(link(&tree, test_dir.join("b").join("to_c").join("to_b")), Some(LinkStatus::Cycle));
+        assert_eq!(link(&tree, test_dir.join("broken")), Some(LinkStatus::Broken));
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+
+    /*
+        Validates that directories failing to be read don't abort the build. The failing
+        directory stays in the tree with an error, its siblings are read, and failures are
+        collected as diagnostics. Same for lazily read directories.
+    */
+    #[test]
+    fn test_partial_reads() {
+        let test_dir = env::temp_dir().join("reasy_test_partial_reads");
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(test_dir.join("a")).unwrap();
+        fs::create_dir_all(test_dir.join("b")).unwrap();
+        fs::write(test_dir.join("a").join("file1.txt"), "test1").unwrap();
+        fs::write(test_dir.join("b").join("file2.txt"), "test2").unwrap();
+
+        //Removed after the first layer was read
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &EditorSettings::default()).unwrap();
+        fs::remove_dir_all(test_dir.join("b")).unwrap();
+        builder.build().unwrap();
+        let mut tree = builder.get_tree();
+
+        assert!(tree.find_by_path(&test_dir.join("a").join("file1.txt")).is_some());
+        let b_id = tree.find_by_path(&test_dir.join("b")).unwrap();
+        assert!(tree.get_node(b_id).unwrap().file_entry.error.is_some());
+        let diagnostics = tree.take_diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].path, test_dir.join("b"));
+        assert!(tree.take_diagnostics().is_empty());
+
+        //Lazy read of the missing directory, then a successful retry
+        tree.apply_loaded(b_id, io::read_directory(&test_dir.join("b"), &EntryRules::default()));
+        assert_eq!(tree.take_diagnostics().len(), 1);
+        fs::create_dir_all(test_dir.join("b")).unwrap();
+        fs::write(test_dir.join("b").join("file2.txt"), "test2").unwrap();
+        tree.apply_loaded(b_id, io::read_directory(&test_dir.join("b"), &EntryRules::default()));
+        assert!(tree.get_node(b_id).unwrap().file_entry.error.is_none());
+        assert!(tree.find_by_path(&test_dir.join("b").join("file2.txt")).is_some());
+
+        //Permissions are not enforced when running as root
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            fs::set_permissions(test_dir.join("a"), fs::Permissions::from_mode(0o000)).unwrap();
+            if fs::read_dir(test_dir.join("a")).is_err(){
+                let mut builder = TreeBuilder::init(Some(test_dir.clone()), &EditorSettings::default()).unwrap();
+                builder.build().unwrap();
+                let mut tree = builder.get_tree();
+
+                let a_id = tree.find_by_path(&test_dir.join("a")).unwrap();
+                assert!(tree.get_node(a_id).unwrap().file_entry.error.is_some());
+                assert!(tree.find_by_path(&test_dir.join("b").join("file2.txt")).is_some());
+                assert_eq!(tree.take_diagnostics().len(), 1);
+            }
+            fs::set_permissions(test_dir.join("a"), fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+}
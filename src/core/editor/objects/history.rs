@@ -0,0 +1,119 @@
+use std::{collections::{HashSet, VecDeque}, path::PathBuf};
+
+use crate::{core::editor::objects::{flat_tree::TreeNode, ui_tree::UIDOperation}, utils::trash::TrashEntry};
+
+
+///Maximum number of operations that can be undone.
+pub(crate) const HISTORY_LIMIT: usize = 100;
+
+///Tree and disk effect of an applied operation, used to revert it.
+#[derive(Debug, Clone)]
+pub(crate) enum AppliedOperation{
+    //Node id, old name
+    Renamed(usize, String),
+    //Created or copied path
+    Created(PathBuf),
+    //New node id, old parent id, old path, new path
+    Moved(usize, Option<usize>, PathBuf, PathBuf),
+    //Original path, removed nodes (node first, none if never read), trashed path
+    Deleted(PathBuf, Vec<TreeNode>, PathBuf),
+    Restored(TrashEntry)
+}
+impl AppliedOperation{
+    ///Ids of the nodes an effect refers to.
+    fn get_ids(&self) -> Vec<usize>{
+        match self{
+            AppliedOperation::Renamed(id, _) => return vec![*id],
+            AppliedOperation::Moved(id, old_parent, _, _) => return [Some(*id), *old_parent].into_iter().flatten().collect(),
+            AppliedOperation::Deleted(_, nodes, _) => return nodes.iter().map(|node| node.id).collect(),
+            AppliedOperation::Created(_) | AppliedOperation::Restored(_) => return Vec::new(),
+        }
+    }
+}
+
+///Executed operation. Undone by reverting its effects, redone by executing the operation again.
+#[derive(Debug, Clone)]
+pub(crate) struct HistoryEntry{
+    pub(crate) label: String,
+    pub(crate) operation: UIDOperation,
+    pub(crate) applied: Vec<AppliedOperation>
+}
+
+///Undo/redo request, from the menu or keyboard shortcuts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HistoryCommand{
+    Undo,
+    Redo
+}
+
+///Labels of the undoable and redoable operations, newest first.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct HistorySummary{
+    pub(crate) undo: Vec<String>,
+    pub(crate) redo: Vec<String>
+}
+
+///Bounded undo and redo stacks of executed operations.
+#[derive(Debug, Clone)]
+pub(crate) struct OperationHistory{
+    undo: VecDeque<HistoryEntry>,
+    redo: Vec<HistoryEntry>,
+    limit: usize
+}
+impl OperationHistory{
+    pub(crate) fn new(limit: usize) -> OperationHistory{
+        return OperationHistory {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            limit: limit
+        }
+    }
+
+    ///Records a newly executed operation, which invalidates the redo stack.
+    pub(crate) fn record(&mut self, entry: HistoryEntry){
+        self.redo.clear();
+        self.push_undo(entry);
+    }
+
+    ///Pushes an entry to the undo stack, dropping the oldest entries above the limit.
+    pub(crate) fn push_undo(&mut self, entry: HistoryEntry){
+        self.undo.push_back(entry);
+
+        while self.undo.len() > self.limit{
+            self.undo.pop_front();
+        }
+    }
+
+    ///Drops the undoable entries that can no longer be reverted.
+    pub(crate) fn retain_undo(&mut self, keep: impl Fn(&HistoryEntry) -> bool){
+        self.undo.retain(|entry| keep(entry));
+    }
+
+    pub(crate) fn push_redo(&mut self, entry: HistoryEntry){
+        self.redo.push(entry);
+    }
+
+    pub(crate) fn pop_undo(&mut self) -> Option<HistoryEntry>{
+        return self.undo.pop_back()
+    }
+
+    pub(crate) fn pop_redo(&mut self) -> Option<HistoryEntry>{
+        return self.redo.pop()
+    }
+
+    ///Ids of the nodes referred to by undoable and redoable operations.
+    pub(crate) fn get_ids(&self) -> HashSet<usize>{
+        return self.undo
+            .iter()
+            .chain(self.redo.iter())
+            .flat_map(|entry| entry.operation.get_ids().into_iter().chain(entry.applied.iter().flat_map(|effect| effect.get_ids())))
+            .collect()
+    }
+
+    pub(crate) fn get_summary(&self) -> HistorySummary{
+        return HistorySummary {
+            undo: self.undo.iter().rev().map(|entry| entry.label.clone()).collect(),
+            redo: self.redo.iter().rev().map(|entry| entry.label.clone()).collect()
+        }
+    }
+}
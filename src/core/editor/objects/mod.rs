@@ -1,3 +1,8 @@
+pub(crate) mod decorations;
+pub(crate) mod filter;
+pub(crate) mod finder;
 pub(crate) mod flat_tree;
+pub(crate) mod history;
 pub(crate) mod settings;
+pub(crate) mod sort;
 pub(crate) mod ui_tree;
\ No newline at end of file
@@ -1,21 +1,41 @@
-use std::path::PathBuf;
+use std::{collections::HashMap, path::{Path, PathBuf}, sync::Arc};
 
 use serde::{Deserialize, Serialize};
 
-use crate::{utils::{error::EditorIoError, io}, EDITOR_ROOT_DIR};
+use crate::{core::editor::objects::{decorations::{Decoration, DecorationRegistry}, sort::SortMode}, utils::{error::EditorIoError, io::{self, FileEntry}, rules::SymlinkPolicy}, EDITOR_ROOT_DIR};
 
 
 ///Configuration struct that holds *ALL* information regarding ui editor.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub(crate) struct EditorSettings{
     //FileTree
-    pub(crate) show_hidden_elements: bool
+    pub(crate) show_hidden_elements: bool,
+    pub(crate) show_ignored_elements: bool,
+    ///Glob patterns of entry names that are treated as hidden.
+    pub(crate) hidden_patterns: Vec<String>,
+    ///Gitignore style patterns excluded from the tree, on top of `.gitignore` and `.ignore` files.
+    pub(crate) exclude_patterns: Vec<String>,
+    ///Read directories only when first expanded.
+    pub(crate) lazy_loading: bool,
+    pub(crate) sort_mode: SortMode,
+    ///Icons and colors added to or replacing the built-in ones. Keys are entry names (`Cargo.toml`),
+    /// extensions (`*.png`) or badges (`@modified`, `@ignored`, `@symlink`, `@symlink_loop`, `@broken_link`, `@error`).
+    pub(crate) decorations: HashMap<String, Decoration>,
+    pub(crate) symlink_policy: SymlinkPolicy
 }
 
 impl Default for EditorSettings{
     fn default() -> EditorSettings {
         return EditorSettings { 
-            show_hidden_elements: false 
+            show_hidden_elements: false,
+            show_ignored_elements: false,
+            hidden_patterns: Vec::new(),
+            exclude_patterns: vec![".git/".to_string()],
+            lazy_loading: true,
+            sort_mode: SortMode::default(),
+            decorations: HashMap::new(),
+            symlink_policy: SymlinkPolicy::default()
         }
     }
 }
@@ -58,17 +78,110 @@ fn get_settings_path() -> PathBuf{
 
 
 
+///File tree state of a project, restored on the next launch. Paths are relative to the project root.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct TreeState{
+    ///Expanded directories, parents first.
+    pub(crate) expanded: Vec<PathBuf>,
+    pub(crate) selection: Vec<PathBuf>,
+    pub(crate) scroll_offset: f32,
+    ///Bookmarked entries, in the order they were pinned.
+    pub(crate) pinned: Vec<PathBuf>
+}
+
+///Data kept per project, in a file named after the project root next to the settings file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct ProjectFile{
+    ///Project root the file belongs to, files of other roots sharing the name are ignored.
+    pub(crate) root: PathBuf,
+    ///Folders shown in the file tree next to the project root. Relative paths are resolved against the project root.
+    pub(crate) workspace_roots: Vec<PathBuf>,
+    pub(crate) tree_state: TreeState
+}
+
+///Reads and returns if exists, the project file of a project root. Else returns default.
+pub(crate) fn load_project(root: &Path) -> Result<ProjectFile, EditorIoError>{
+    let path = get_project_path(root);
+
+    if path.try_exists()?{
+        let project: ProjectFile = io::read_serialized_data(path)?;
+        if project.root == root{
+            return Ok(project)
+        }
+    }
+
+    return Ok(ProjectFile {
+        root: root.to_path_buf(),
+        ..ProjectFile::default()
+    })
+}
+
+///Saves a project file next to the settings file.
+pub(crate) fn save_project(project: &ProjectFile) -> Result<(), EditorIoError>{
+    let path = get_project_path(&project.root);
+    if let Some(directory) = path.parent().filter(|directory| !directory.exists()){
+        io::create_entry(directory, true)?;
+    }
+
+    return io::write_serialized_data(project, path)
+}
+
+///Saves the file tree state into the project file, keeping the rest of it.
+pub(crate) fn save_tree_state(root: &Path, state: &TreeState) -> Result<(), EditorIoError>{
+    let mut project = load_project(root)?;
+    project.tree_state = state.clone();
+
+    return save_project(&project)
+}
+
+///Saves the workspace folders into the project file, keeping the rest of it.
+pub(crate) fn save_workspace_roots(root: &Path, roots: &[PathBuf]) -> Result<(), EditorIoError>{
+    let mut project = load_project(root)?;
+    project.workspace_roots = roots.to_vec();
+
+    return save_project(&project)
+}
+
+///Helper function to point to the project file of a project root, in the `projects` directory next to the settings file.
+/// Named after the root folder and a hash of its full path.
+fn get_project_path(root: &Path) -> PathBuf{
+    let name = root.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    //FNV-1a, stable between builds unlike the std hasher
+    let hash = root.to_string_lossy()
+        .bytes()
+        .fold(0xcbf29ce484222325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3));
+
+    return get_settings_path().with_file_name("projects").join(format!("{}-{:016x}.json", name, hash))
+}
+
+
+
 
 
 ///Configuration that are related to UI preferences.
 #[derive(Clone, Debug)]
 pub(crate) struct FileTreeSettings{
-    pub(crate) show_hidden_elements: bool
+    pub(crate) show_hidden_elements: bool,
+    pub(crate) show_ignored_elements: bool,
+    pub(crate) sort_mode: SortMode,
+    pub(crate) decorations: Arc<DecorationRegistry>
+}
+impl FileTreeSettings{
+    ///Returns true if an entry should be shown, based on its hidden and ignored properties.
+    pub(crate) fn shows(&self, entry: &FileEntry) -> bool{
+        return (self.show_hidden_elements || !entry.is_hidden) 
+            && (self.show_ignored_elements || !entry.is_ignored)
+    }
 }
 impl From<EditorSettings> for FileTreeSettings{
     fn from(value: EditorSettings) -> Self {
         return FileTreeSettings { 
-            show_hidden_elements: value.show_hidden_elements 
+            show_hidden_elements: value.show_hidden_elements,
+            show_ignored_elements: value.show_ignored_elements,
+            sort_mode: value.sort_mode,
+            decorations: Arc::new(DecorationRegistry::new(&value.decorations))
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,151 @@
+use core::fmt;
+use std::{cmp::Ordering, str::Chars, iter::Peekable};
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::io::FileEntry;
+
+
+///Order of siblings in the file tree. Directories are always listed before files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub(crate) enum SortMode{
+    ///Byte order of names.
+    #[default]
+    Name,
+    ///Numbers inside names are compared by value, "file2" before "file10".
+    Natural,
+    CaseInsensitive,
+    Extension,
+    ///Largest first.
+    Size,
+    ///Most recently modified first.
+    Modified
+}
+
+impl SortMode{
+    pub(crate) const ALL: [SortMode; 6] = [
+        SortMode::Name,
+        SortMode::Natural,
+        SortMode::CaseInsensitive,
+        SortMode::Extension,
+        SortMode::Size,
+        SortMode::Modified
+    ];
+
+    ///Returns true if the order depends on entry metadata, which changes along with the contents.
+    pub(crate) fn uses_metadata(&self) -> bool{
+        return matches!(self, SortMode::Size | SortMode::Modified)
+    }
+
+    ///Compares two sibling entries. Ties are broken by natural name order, then byte order.
+    pub(crate) fn compare(&self, first: &FileEntry, second: &FileEntry) -> Ordering{
+        match (first.is_dir, second.is_dir) {
+            (true, false) => return Ordering::Less,
+            (false, true) => return Ordering::Greater,
+            _ => {}
+        }
+
+        let ordering = match self{
+            SortMode::Name => return first.name.cmp(&second.name),
+            SortMode::Natural => Ordering::Equal,
+            SortMode::CaseInsensitive => first.name.to_lowercase().cmp(&second.name.to_lowercase()),
+            SortMode::Extension => get_extension(first).cmp(&get_extension(second)),
+            SortMode::Size => second.size.cmp(&first.size),
+            SortMode::Modified => second.modified.cmp(&first.modified),
+        };
+
+        return ordering
+            .then_with(|| natural_cmp(&first.name, &second.name))
+            .then_with(|| first.name.cmp(&second.name))
+    }
+}
+
+impl fmt::Display for SortMode{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self{
+            SortMode::Name => write!(f, "Name"),
+            SortMode::Natural => write!(f, "Natural"),
+            SortMode::CaseInsensitive => write!(f, "Name (case insensitive)"),
+            SortMode::Extension => write!(f, "Extension"),
+            SortMode::Size => write!(f, "Size"),
+            SortMode::Modified => write!(f, "Date modified"),
+        }
+    }
+}
+
+///Lowercase extension of a file, empty for directories and files without one.
+fn get_extension(entry: &FileEntry) -> String{
+    if entry.is_dir{
+        return String::new()
+    }
+
+    return entry.path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+///Compares names by chunks, where digit runs are compared by value and text case-insensitively.
+pub(crate) fn natural_cmp(first: &str, second: &str) -> Ordering{
+    let mut first = first.chars().peekable();
+    let mut second = second.chars().peekable();
+
+    loop{
+        let ordering = match (first.peek().copied(), second.peek().copied()){
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a), Some(b)) if a.is_ascii_digit() && b.is_ascii_digit() => {
+                compare_numbers(&take_digits(&mut first), &take_digits(&mut second))
+            },
+            (Some(a), Some(b)) => {
+                first.next();
+                second.next();
+                a.to_lowercase().cmp(b.to_lowercase())
+            },
+        };
+
+        if ordering != Ordering::Equal{
+            return ordering
+        }
+    }
+}
+
+fn take_digits(chars: &mut Peekable<Chars>) -> String{
+    let mut digits = String::new();
+
+    while let Some(digit) = chars.next_if(|c| c.is_ascii_digit()){
+        digits.push(digit);
+    }
+
+    return digits
+}
+
+///Compares digit runs by value without parsing, so any length is supported.
+fn compare_numbers(first: &str, second: &str) -> Ordering{
+    let first_trimmed = first.trim_start_matches('0');
+    let second_trimmed = second.trim_start_matches('0');
+
+    return first_trimmed.len().cmp(&second_trimmed.len())
+        .then_with(|| first_trimmed.cmp(second_trimmed))
+        //Fewer leading zeros first
+        .then_with(|| first.len().cmp(&second.len()))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /*
+        Validates natural order of names with numbers, text case and leading zeros.
+    */
+    #[test]
+    fn test_natural_cmp() {
+        let mut names = vec!["file10.txt", "file2.txt", "File1.txt", "file02.txt", "file", "a100000000000000000000000"];
+        names.sort_by(|first, second| natural_cmp(first, second));
+
+        assert_eq!(names, vec!["a100000000000000000000000", "file", "File1.txt", "file2.txt", "file02.txt", "file10.txt"]);
+        assert_eq!(natural_cmp("abc", "ABC"), Ordering::Equal);
+    }
+}
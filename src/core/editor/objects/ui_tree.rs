@@ -1,34 +1,561 @@
 use core::f32;
-use std::{collections::{HashMap, HashSet}, os::windows::fs::MetadataExt, path::PathBuf};
+use std::{cell::RefCell, collections::{HashMap, HashSet}, path::{Path, PathBuf}, rc::Rc, time::{Duration, Instant}};
 
 use egui_tiles::{Tiles, Tree, UiResponse};
 
-use crate::{core::editor::objects::{flat_tree::{FlatTree, TreeBuilder}, settings::{EditorSettings, FileTreeSettings}}, utils::error::EditorIoError};
+use crate::{core::editor::objects::{decorations::{Badge, Decoration}, filter::{FilterMode, TreeFilter}, finder::FileFinder, flat_tree::{FlatTree, NodeIds, PendingLayers, TreeBuilder, TreeNode}, history::{AppliedOperation, HistoryCommand, HistoryEntry, HistorySummary, OperationHistory, HISTORY_LIMIT}, settings::{self, EditorSettings, FileTreeSettings, TreeState}}, event::UserEvent, utils::{error::{EditorIoError, ErrorType}, io::{self, FileEntry, ReadError}, loader::{BackgroundBuild, BuildProgress, DirectoryLoader}, trash::{Trash, TrashEntry}, watcher::{FileWatcher, TreeDelta}}, USER_EVENT_PROXY};
 
 
 
+///Time during which watcher changes of entries written by the editor itself are not flagged as modified.
+const OWN_WRITE_WINDOW: Duration = Duration::from_secs(2);
+
 ///Component used to track and render file systems via a FlatTree structure.
 /// Keeps the whole tree in `flat_tree` and a cached version of the visible 
 /// nodes in `display_tree`.
 /// 
-/// Recomputes the tree to be displayed in every frame via the cached `display_tree`.
+/// The cached `display_tree` is only recomputed when the tree or the settings changed,
+/// see `refresh`. Changes performed outside the editor are received via `watchers`, one per root folder.
 #[derive(Debug, Clone)]
 pub(crate) struct UiDirectory{
     flat_tree: FlatTree,
     display_tree: Vec<usize>,
     operations: Vec<UIDOperation>,
+    watchers: Vec<Rc<FileWatcher>>,
+    loader: Rc<DirectoryLoader>,
+    finder: Option<FileFinder>,
+    filter: TreeFilter,
+    //Node the pane has to scroll to
+    scroll_to: Option<usize>,
+    //Last scroll offset of the pane, and offset to restore once the saved state is applied
+    scroll_offset: f32,
+    restore_scroll: Option<f32>,
+    //Saved state waiting for lazily read directories
+    restoring: Option<TreeState>,
+    //Display tree has to be recomputed
+    dirty: bool,
+    selection: HashSet<usize>,
+    //Node range selections start from
+    selection_anchor: Option<usize>,
+    clipboard: Option<(ClipboardMode, Vec<usize>)>,
+    //Files changed on disk since the editor was opened
+    modified: HashSet<usize>,
+    //Entries written by operations, with the time of writing
+    own_writes: Vec<(PathBuf, Instant)>,
+    //Message of the last failed operation or read, per node
+    errors: HashMap<usize, String>,
+    //Bookmarked nodes, ids follow them through renames and moves
+    pinned: Vec<usize>,
+    //Resolved entries of the pinned nodes, cleared when the tree or the pins change
+    pinned_entries: RefCell<Option<Vec<(usize, FileEntry)>>>,
+    //Remaining layers read on worker threads, with the folder each build reads
+    builds: Vec<(PathBuf, BackgroundBuild)>,
+    //Deleted entries
+    trash: Trash,
+    history: OperationHistory,
     pub(crate) user_input: Option<String>
 }
 impl UiDirectory{
+    pub(crate) fn new(flat_tree: FlatTree, watchers: Vec<Rc<FileWatcher>>) -> UiDirectory{
+        let mut trash = Trash::open(flat_tree.get_root());
+        for root in flat_tree.get_roots().into_iter().skip(1){
+            trash.add_root(root);
+        }
+
+        return UiDirectory { 
+            flat_tree: flat_tree,
+            display_tree: Vec::new(),
+            operations: Vec::new(),
+            watchers: watchers,
+            loader: Rc::new(DirectoryLoader::new()),
+            finder: None,
+            filter: TreeFilter::default(),
+            scroll_to: None,
+            scroll_offset: 0.0,
+            restore_scroll: None,
+            restoring: None,
+            dirty: true,
+            selection: HashSet::new(),
+            selection_anchor: None,
+            clipboard: None,
+            modified: HashSet::new(),
+            own_writes: Vec::new(),
+            errors: HashMap::new(),
+            pinned: Vec::new(),
+            pinned_entries: RefCell::new(None),
+            builds: Vec::new(),
+            trash: trash,
+            history: OperationHistory::new(HISTORY_LIMIT),
+            user_input: None
+        }
+    }
+
+    ///Rebuilds the tree if the symlink policy of `settings` changed. Ids, history, badges,
+    /// expansion and selection are kept.
+    pub(crate) fn sync_workspace(&mut self, settings: &EditorSettings){
+        let root = self.flat_tree.get_root().to_path_buf();
+        if self.flat_tree.get_rules(&root).get_symlink_policy() == settings.symlink_policy{
+            return;
+        }
+
+        let state = self.get_state();
+        let roots = self.get_workspace_roots();
+        match create_directory(settings, self.flat_tree.get_ids().clone()){
+            Ok(mut directory) => {
+                //Layers read with the previous policy are no longer needed
+                for (_, build) in self.builds.drain(..){
+                    build.cancel();
+                }
+                self.builds = std::mem::take(&mut directory.builds);
+                self.flat_tree = directory.flat_tree;
+                self.watchers = directory.watchers;
+                self.selection.clear();
+                self.selection_anchor = None;
+                self.set_workspace_roots(&roots, settings);
+                self.restore_state(state);
+                self.dirty = true;
+            },
+            Err(err) => eprintln!("Failed rebuilding workspace: {}", err),
+        }
+    }
+
+    ///Workspace folders mounted next to the project root.
+    pub(crate) fn get_workspace_roots(&self) -> Vec<PathBuf>{
+        return self.flat_tree.get_roots().into_iter().skip(1).map(|path| path.to_path_buf()).collect()
+    }
+
+    ///Mounts the folders of `roots` that aren't part of the tree yet and unmounts the ones no longer listed,
+    /// leaving the other folders, their ids, badges and watchers untouched. Folders are resolved against the project root, see `io::resolve_path`.
+    pub(crate) fn set_workspace_roots(&mut self, roots: &[PathBuf], settings: &EditorSettings){
+        let project = self.flat_tree.get_root().to_path_buf();
+        let requested: Vec<PathBuf> = roots.iter().map(|path| io::resolve_path(&project, path)).collect();
+        let current = self.get_workspace_roots();
+
+        for removed in current.iter().filter(|path| !requested.contains(path)){
+            self.flat_tree.unmount(removed);
+            self.watchers.retain(|watcher| watcher.get_root() != removed);
+            self.builds.retain(|(root, build)| {
+                if root != removed{
+                    return true
+                }
+                build.cancel();
+                return false
+            });
+        }
+
+        //Without an event loop (e.g. in tests) folders are read right away
+        let background = USER_EVENT_PROXY.get().is_some();
+        for added in requested.iter().filter(|path| !current.contains(path)){
+            match TreeBuilder::mount_root(&mut self.flat_tree, added, settings, background){
+                Ok(pending) => {
+                    self.trash.add_root(added);
+                    self.watchers.extend(watch(added));
+                    self.start_build(added, pending);
+                },
+                Err(err) => eprintln!("Failed mounting workspace folder {}: {}", added.display(), err),
+            }
+        }
+
+        self.selection.retain(|id| self.flat_tree.contains(*id));
+        if self.selection_anchor.is_some_and(|id| !self.flat_tree.contains(id)){
+            self.selection_anchor = None;
+        }
+        self.dirty = true;
+    }
+
+    ///Reads the remaining layers of a folder in the background, sent back through the event loop.
+    fn start_build(&mut self, root: &Path, pending: Option<PendingLayers>){
+        let Some(pending) = pending else{
+            return;
+        };
+
+        if let Some(proxy) = USER_EVENT_PROXY.get(){
+            let proxy = proxy.clone();
+            let build = BackgroundBuild::start(vec![pending], move |event| proxy.send_event(event).is_ok());
+            self.builds.push((root.to_path_buf(), build));
+        }
+    }
+
+    ///Applies a layer or the end of a background build. Events of cancelled builds are ignored.
+    pub(crate) fn apply_build_event(&mut self, event: UserEvent){
+        match event{
+            UserEvent::TreeLayer { build_id, layer, progress } => {
+                let Some((_, build)) = self.builds.iter_mut().find(|(_, build)| build.get_id() == build_id) else{
+                    return;
+                };
+                build.set_progress(progress);
+                self.flat_tree.apply_layer(layer);
+            },
+            UserEvent::TreeBuilt { build_id } => {
+                let count = self.builds.len();
+                self.builds.retain(|(_, build)| build.get_id() != build_id);
+                if self.builds.len() == count{
+                    return;
+                }
+            },
+            _ => return,
+        }
+        self.apply_restoring();
+    }
+
+    ///Progress of the background builds combined, None once all finished.
+    pub(crate) fn get_build_progress(&self) -> Option<BuildProgress>{
+        if self.builds.is_empty(){
+            return None
+        }
+
+        return Some(self.builds.iter().fold(BuildProgress::default(), |total, (_, build)| {
+            let progress = build.get_progress();
+            BuildProgress { read: total.read + progress.read, queued: total.queued + progress.queued }
+        }))
+    }
+
+    ///Returns true while the filter or the file finder is in use.
+    pub(crate) fn is_searching(&self) -> bool{
+        return self.filter.is_active() || self.finder.is_some()
+    }
+
+    ///Flags the display tree for recomputation on the next `refresh`.
+    pub(crate) fn mark_dirty(&mut self){
+        self.dirty = true;
+    }
+
+    ///Reloads the display tree only if it was flagged or the underlying tree changed. Returns true if reloaded.
+    pub(crate) fn refresh(&mut self, settings: FileTreeSettings) -> bool{
+        self.flat_tree.set_sort_mode(settings.sort_mode);
+        let tree_changed = self.flat_tree.take_dirty();
+        if !self.dirty && !tree_changed{
+            return false
+        }
+
+        self.invalidate_pinned();
+        self.reload(settings);
+        return true
+    }
+
+    ///Expands all ancestors of a node and scrolls the pane to it.
+    pub(crate) fn reveal(&mut self, id: usize){
+        self.flat_tree.reveal(id);
+        self.scroll_to = Some(id);
+    }
+
+    ///Requests reading of newly expanded directories and applies finished reads.
+    pub(crate) fn sync_loads(&mut self){
+        let requests = self.flat_tree.take_pending_loads()
+            .into_iter()
+            .map(|(id, path)| {
+                let rules = self.flat_tree.get_rules(&path).clone();
+                (id, path, rules)
+            })
+            .collect();
+        self.loader.request(requests);
+
+        //Failed reads are flagged on the nodes and reported as diagnostics
+        for (id, directory) in self.loader.poll(){
+            self.flat_tree.apply_loaded(id, directory);
+        }
+        self.apply_restoring();
+    }
+
+    ///Returns the status badges of a node.
+    pub(crate) fn get_badges(&self, node: &TreeNode) -> Vec<Badge>{
+        let mut badges = Vec::new();
+
+        if self.modified.contains(&node.id){
+            badges.push(Badge::Modified);
+        }
+        if node.file_entry.is_ignored{
+            badges.push(Badge::Ignored);
+        }
+        if let Some(status) = node.file_entry.link{
+            badges.push(Badge::Symlink(status));
+        }
+        if let Some(message) = self.errors.get(&node.id).or(node.file_entry.error.as_ref()){
+            badges.push(Badge::Error(message.clone()));
+        }
+
+        return badges
+    }
+
+    ///Takes the read failures collected since the last call.
+    pub(crate) fn take_diagnostics(&mut self) -> Vec<ReadError>{
+        return self.flat_tree.take_diagnostics()
+    }
+
+    ///Project root of the tree, which keys the saved state.
+    pub(crate) fn get_root(&self) -> &Path{
+        return self.flat_tree.get_root()
+    }
+
+    ///Returns the expansion, selection and scroll state of the tree, to be saved between sessions.
+    pub(crate) fn get_state(&self) -> TreeState{
+        let root = self.flat_tree.get_root();
+        let relative = |path: PathBuf| path.strip_prefix(root).map(|path| path.to_path_buf()).unwrap_or(path);
+
+        let mut selection: Vec<PathBuf> = self.selection
+            .iter()
+            .filter_map(|id| self.flat_tree.get_path(*id))
+            .map(relative)
+            .collect();
+        let mut expanded: Vec<PathBuf> = self.flat_tree.get_expanded_paths().into_iter().map(relative).collect();
+        let mut scroll_offset = self.scroll_offset;
+        //Parts of a state still being restored are kept
+        if let Some(restoring) = &self.restoring{
+            expanded.extend(restoring.expanded.iter().cloned());
+            selection.extend(restoring.selection.iter().cloned());
+            scroll_offset = restoring.scroll_offset;
+        }
+        selection.sort();
+
+        return TreeState {
+            expanded: expanded,
+            selection: selection,
+            scroll_offset: scroll_offset,
+            pinned: self.pinned.iter().filter_map(|id| self.flat_tree.get_known_path(*id)).map(relative).collect()
+        }
+    }
+
+    ///Restores a saved state. Directories of a lazy tree are expanded as they are read,
+    /// and the scroll offset is applied once nothing is left to restore.
+    pub(crate) fn restore_state(&mut self, state: TreeState){
+        let root = self.flat_tree.get_root().to_path_buf();
+        //Pinned nodes not read yet take their id once loaded
+        self.pinned = state.pinned
+            .iter()
+            .map(|path| self.flat_tree.get_or_allocate_id(&root.join(path)))
+            .collect();
+        self.invalidate_pinned();
+
+        self.restoring = Some(state);
+        self.apply_restoring();
+    }
+
+    pub(crate) fn is_pinned(&self, id: usize) -> bool{
+        return self.pinned.contains(&id)
+    }
+
+    ///Pins a node to the bookmarks, or unpins it if already pinned.
+    pub(crate) fn toggle_pin(&mut self, id: usize){
+        match self.pinned.iter().position(|pinned| *pinned == id){
+            Some(index) => {
+                self.pinned.remove(index);
+            },
+            None => self.pinned.push(id),
+        }
+        self.invalidate_pinned();
+    }
+
+    ///Pinned nodes with their current entries. Nodes removed from disk are left out, but stay pinned
+    /// so that they reappear if restored. Entries are resolved once per change of the tree, nodes not read yet from disk.
+    pub(crate) fn get_pinned(&self) -> Vec<(usize, FileEntry)>{
+        if let Some(entries) = self.pinned_entries.borrow().as_ref(){
+            return entries.clone()
+        }
+
+        let entries: Vec<(usize, FileEntry)> = self.pinned
+            .iter()
+            .filter_map(|id| match self.flat_tree.get_children_from_ids(&[*id]).first(){
+                Some(node) => Some((*id, node.file_entry.clone())),
+                None => {
+                    let path = self.flat_tree.get_known_path(*id)?;
+                    let metadata = std::fs::metadata(&path).ok()?;
+                    Some((*id, FileEntry::new(path, metadata)))
+                },
+            })
+            .collect();
+        *self.pinned_entries.borrow_mut() = Some(entries.clone());
+
+        return entries
+    }
+
+    ///Clears the resolved pinned entries, resolved again when next requested.
+    fn invalidate_pinned(&self){
+        self.pinned_entries.borrow_mut().take();
+    }
+
+    ///Reveals and selects a pinned node, expanding it if it is a directory. Nodes of
+    /// directories not read yet are revealed as their ancestors are loaded.
+    pub(crate) fn open_pinned(&mut self, id: usize){
+        if self.flat_tree.contains(id){
+            self.reveal(id);
+            if self.flat_tree.get_children_from_ids(&[id]).iter().any(|node| node.file_entry.is_dir){
+                self.flat_tree.expand(id);
+            }
+            self.clear_selection();
+            self.selection.insert(id);
+            self.selection_anchor = Some(id);
+            return;
+        }
+
+        let path = match self.flat_tree.get_known_path(id){
+            Some(path) => path,
+            None => return,
+        };
+        let root = self.flat_tree.get_roots().into_iter().find(|root| path.starts_with(root)).map(|root| root.to_path_buf());
+        //Every ancestor below the root, parents first
+        let mut expanded: Vec<PathBuf> = path.ancestors()
+            .skip(1)
+            .take_while(|ancestor| root.as_ref().is_some_and(|root| ancestor.starts_with(root) && ancestor != root))
+            .map(|ancestor| ancestor.to_path_buf())
+            .collect();
+        expanded.reverse();
+        if path.is_dir(){
+            expanded.push(path.clone());
+        }
+
+        self.clear_selection();
+        self.restoring = Some(TreeState {
+            expanded: expanded,
+            selection: vec![path],
+            scroll_offset: self.scroll_offset,
+            pinned: Vec::new()
+        });
+        self.apply_restoring();
+    }
+
+    ///Applies the parts of the saved state whose nodes exist by now.
+    fn apply_restoring(&mut self){
+        let state = match &mut self.restoring{
+            Some(state) => state,
+            None => return,
+        };
+        let root = self.flat_tree.get_root().to_path_buf();
+
+        //Parents come first, so their children are visible when reached
+        state.expanded.retain(|path| {
+            match self.flat_tree.find_by_path(&root.join(path)){
+                Some(id) => {
+                    self.flat_tree.expand(id);
+                    false
+                },
+                None => true,
+            }
+        });
+        state.selection.retain(|path| {
+            match self.flat_tree.find_by_path(&root.join(path)){
+                Some(id) => {
+                    self.selection.insert(id);
+                    false
+                },
+                None => true,
+            }
+        });
+
+        //Paths that no longer exist are dropped once no directory is being read
+        let finished = state.expanded.is_empty() && state.selection.is_empty();
+        if finished || (!self.flat_tree.is_loading() && self.builds.is_empty()){
+            self.restore_scroll = Some(state.scroll_offset);
+            self.restoring = None;
+            self.dirty = true;
+        }
+    }
+
+    ///Applies pending filesystem changes to the tree.
+    pub(crate) fn sync_with_disk(&mut self){
+        let deltas: Vec<TreeDelta> = self.watchers
+            .iter()
+            .flat_map(|watcher| watcher.poll())
+            .collect();
+        if deltas.is_empty(){
+            return;
+        }
+
+        self.apply_deltas(deltas);
+    }
+
+    ///Applies filesystem changes to the tree. Files changed by anything but the editor's own operations are flagged as modified.
+    fn apply_deltas(&mut self, deltas: Vec<TreeDelta>){
+        self.own_writes.retain(|(_, written)| written.elapsed() < OWN_WRITE_WINDOW);
+
+        for delta in deltas{
+            //Edited ignore files change which entries are ignored
+            let changed_paths = match &delta{
+                TreeDelta::Rename(from, to) => vec![from, to],
+                TreeDelta::Insert(path) | TreeDelta::Remove(path) | TreeDelta::Modify(path) => vec![path],
+            };
+            for path in changed_paths{
+                self.flat_tree.apply_ignore_change(path);
+            }
+
+            let result = match &delta{
+                TreeDelta::Insert(path) => self.flat_tree.insert_path(path),
+                TreeDelta::Remove(path) => Ok(self.flat_tree.remove_path(path).is_some()),
+                TreeDelta::Rename(from, to) => self.flat_tree.rename_path(from, to),
+                //Directories change along with their children, only files are flagged
+                TreeDelta::Modify(path) => {
+                    let own_write = self.own_writes.iter().any(|(written, _)| path.starts_with(written));
+                    self.flat_tree.refresh_entry(path)
+                        .map(|id| id.filter(|_| path.is_file() && !own_write).is_some_and(|id| self.modified.insert(id)))
+                },
+            };
+
+            if let Err(err) = result{
+                eprintln!("Failed applying {:?}: {}", delta, err);
+            }
+            if let TreeDelta::Rename(_, to) = &delta{
+                if let Some(id) = self.flat_tree.find_by_path(to).filter(|_| !self.builds.is_empty()){
+                    self.flat_tree.unload(id);
+                }
+            }
+        }
+        self.prune_ids();
+        self.invalidate_pinned();
+    }
+
+    ///Remembers the entries written by applied effects, so that the watcher changes they cause aren't flagged as modified.
+    fn note_own_writes(&mut self, applied: &[AppliedOperation]){
+        let now = Instant::now();
+
+        for effect in applied{
+            let path = match effect{
+                AppliedOperation::Renamed(id, _) => self.flat_tree.get_path(*id),
+                AppliedOperation::Created(path) | AppliedOperation::Moved(_, _, _, path) => Some(path.clone()),
+                AppliedOperation::Restored(entry) => Some(entry.original_path.clone()),
+                AppliedOperation::Deleted(..) => None,
+            };
+            if let Some(path) = path{
+                self.own_writes.push((path, now));
+            }
+        }
+    }
+
+    ///Directories renamed or moved while being built miss the layers read by their old path, their contents are read again instead.
+    fn unload_moved(&mut self, applied: &[AppliedOperation]){
+        if self.builds.is_empty(){
+            return;
+        }
+
+        for effect in applied{
+            if let AppliedOperation::Renamed(id, _) | AppliedOperation::Moved(id, ..) = effect{
+                self.flat_tree.unload(*id);
+            }
+        }
+    }
+
+    ///Forgets the ids of removed entries, unless the history, pins, selection or clipboard still refer to them.
+    fn prune_ids(&mut self){
+        let mut referenced = self.history.get_ids();
+        referenced.extend(self.pinned.iter().chain(self.selection.iter()));
+        if let Some((_, ids)) = &self.clipboard{
+            referenced.extend(ids.iter());
+        }
+
+        self.flat_tree.prune_ids(&referenced);
+    }
+
     pub(crate) fn reload(&mut self, settings: FileTreeSettings){
+        //Directories of lazy trees are read as a whole while searching
+        if self.is_searching(){
+            self.flat_tree.load_unread(|entry| settings.shows(entry));
+        }
+
         let visible_nodes = self.flat_tree.get_visible_items();
         
         let mut dirs_to_collapse = Vec::new();
         
         //First pass for dir collection
         for node in visible_nodes.iter() {
-            let is_hidden = node.file_entry.metadata.file_attributes() & 0x2 != 0;
-            let show = settings.show_hidden_elements || !is_hidden;
+            let show = settings.shows(&node.file_entry);
             
             //If node is dir, is expanded and shouldn't be visible, toggle it
             if !show && node.file_entry.is_dir && node.expanded {
@@ -50,14 +577,39 @@ impl UiDirectory{
         
         for node in updated_visible_nodes.iter() {
             
-            //IF root depth, or has visible parent 
-            if node.depth == 0 || visible_parent_ids.contains(&node.parent){
+            //IF shown and root depth, or has visible parent 
+            if settings.shows(&node.file_entry) && (node.depth == 0 || visible_parent_ids.contains(&node.parent)){
                 display_tree.push(node.id);
                 visible_parent_ids.insert(node.id);
             }
         }
         
+        //Filtered nodes replace the visible ones, expansion state is left untouched
+        if self.filter.is_active(){
+            display_tree.clear();
+            visible_parent_ids.clear();
+
+            for node in self.flat_tree.get_filtered_items(&self.filter){
+                if settings.shows(&node.file_entry) && (node.depth == 0 || visible_parent_ids.contains(&node.parent)){
+                    display_tree.push(node.id);
+                    visible_parent_ids.insert(node.id);
+                }
+            }
+        }
+        
         self.display_tree = display_tree;
+        //Drop selected and badged nodes that no longer exist
+        let tree = &self.flat_tree;
+        self.selection.retain(|id| tree.contains(*id));
+        self.modified.retain(|id| tree.contains(*id));
+        self.errors.retain(|id, _| tree.contains(*id));
+        //Results follow the directories read meanwhile
+        if let Some(finder) = &mut self.finder{
+            finder.update(tree, &settings);
+        }
+        //Collapsed directories are already part of the new display tree
+        self.flat_tree.take_dirty();
+        self.dirty = false;
     }
 
     pub(crate) fn queue_operation(&mut self, operation: UIDOperation){
@@ -68,31 +620,591 @@ impl UiDirectory{
         self.operations.push(operation);
     }
 
+    ///Selects a node. `toggle` adds or removes it from the selection (Ctrl-click), `range` selects every
+    /// displayed node between the last selected node and this one (Shift-click).
+    pub(crate) fn select(&mut self, id: usize, toggle: bool, range: bool){
+        let anchor_index = self.selection_anchor.and_then(|anchor| self.display_tree.iter().position(|node| *node == anchor));
+        let index = self.display_tree.iter().position(|node| *node == id);
+
+        if let (true, Some(anchor_index), Some(index)) = (range, anchor_index, index){
+            if !toggle{
+                self.selection.clear();
+            }
+            let (start, end) = (anchor_index.min(index), anchor_index.max(index));
+            self.selection.extend(self.display_tree[start..=end].iter().copied());
+            return;
+        }
+
+        if toggle{
+            if !self.selection.remove(&id){
+                self.selection.insert(id);
+            }
+        }
+        else{
+            self.selection.clear();
+            self.selection.insert(id);
+        }
+        self.selection_anchor = Some(id);
+    }
+
+    pub(crate) fn is_selected(&self, id: usize) -> bool{
+        return self.selection.contains(&id)
+    }
+
+    pub(crate) fn clear_selection(&mut self){
+        self.selection.clear();
+        self.selection_anchor = None;
+    }
+
+    ///Returns the selected nodes whose ancestors are not selected, as operating on 
+    /// a directory already includes its descendants.
+    pub(crate) fn selected_roots(&self) -> Vec<usize>{
+        let mut roots = self.selection
+            .iter()
+            .copied()
+            .filter(|id| {
+                let mut current = self.flat_tree.get_parent_id(*id);
+                while let Some(ancestor) = current{
+                    if self.selection.contains(&ancestor){
+                        return false
+                    }
+                    current = self.flat_tree.get_parent_id(ancestor);
+                }
+                true
+            })
+            .collect::<Vec<usize>>();
+        roots.sort_by_key(|id| self.display_tree.iter().position(|node| node == id));
+
+        return roots
+    }
+
+    ///Creates a compound operation applying `operation` to every selected root node.
+    pub(crate) fn batch_operation(&self, operation: impl Fn(usize) -> UIDOperation) -> UIDOperation{
+        return UIDOperation::BATCH(self.selected_roots().into_iter().map(operation).collect())
+    }
+
+    ///Places nodes in the clipboard, replacing its contents.
+    pub(crate) fn set_clipboard(&mut self, mode: ClipboardMode, ids: Vec<usize>){
+        if ids.is_empty(){
+            return;
+        }
+        self.clipboard = Some((mode, ids));
+    }
+
+    ///Queues the clipboard contents to be copied or moved into a target directory (`None` for the top level).
+    /// Cut nodes can only be pasted once.
+    pub(crate) fn paste(&mut self, target_id: Option<usize>){
+        let operations = match &self.clipboard{
+            Some((ClipboardMode::Copy, ids)) => ids.iter().map(|id| UIDOperation::COPY(*id, target_id)).collect(),
+            Some((ClipboardMode::Cut, ids)) => ids.iter().map(|id| UIDOperation::MOVE(*id, target_id)).collect(),
+            None => return,
+        };
+
+        if self.clipboard.as_ref().is_some_and(|(mode, _)| *mode == ClipboardMode::Cut){
+            self.clipboard = None;
+        }
+        self.queue_operation(UIDOperation::BATCH(operations));
+    }
+
+    ///Directory pasting targets by default, the last selected directory or the parent of the last selected file.
+    pub(crate) fn paste_target(&self) -> Option<usize>{
+        return match self.selection_anchor{
+            Some(anchor) if self.flat_tree.get_path(anchor).is_some_and(|path| path.is_dir()) => Some(anchor),
+            Some(anchor) => self.flat_tree.get_parent_id(anchor),
+            None => None,
+        }
+    }
+
     ///Executes operation queue. Manual reloading is needed after to apply changes.
-    pub(crate) fn execute_operations(&mut self){
+    /// Every operation is applied to the FlatTree and then submitted to the filesystem,
+    /// if the filesystem operation fails the tree is rolled back. Returns the failed operations errors.
+    /// Executed operations are recorded in the undo history.
+    pub(crate) fn execute_operations(&mut self) -> Vec<EditorIoError>{
+        let mut errors = Vec::new();
+        if self.operations.is_empty(){
+            return errors
+        }
+
         while let Some(operation) = self.operations.pop(){
-            match operation{
-                UIDOperation::RENAME(id, new_name) => {
-                    if let Some(old_name) = self.flat_tree.rename(id, &new_name){
-                        println!("Renamed node: {} from {} to {}", id, old_name, new_name);
-                    }
+            let label = self.describe(&operation);
+            let result = self.apply_operation(operation.clone())
+                .and_then(|applied| self.commit(&applied).map(|_| applied));
+
+            for id in operation.get_ids(){
+                match &result{
+                    Ok(_) => self.errors.remove(&id),
+                    Err(err) => self.errors.insert(id, err.get_message().to_string()),
+                };
+            }
+
+            match result{
+                Ok(applied) if !applied.is_empty() => {
+                    self.note_own_writes(&applied);
+                    self.unload_moved(&applied);
+                    self.history.record(HistoryEntry { 
+                        label: label, 
+                        operation: operation, 
+                        applied: applied 
+                    });
                 },
-                UIDOperation::DELETE(id) => {
-                    if let Some(node) = self.flat_tree.remove(id){
-                        println!("Removed node: {}", node.file_entry.path.display());
+                Ok(_) => {},
+                Err(err) => {
+                    eprintln!("{}", err);
+                    errors.push(err);
+                },
+            }
+        }
+        self.prune_ids();
+        self.invalidate_pinned();
+
+        return errors
+    }
+
+    ///Reverts the last executed operation on disk and in the tree.
+    /// If an effect fails to be reverted, the already reverted ones are applied again
+    /// and the operation stays undoable, unless an entry it refers to is gone for good.
+    pub(crate) fn undo(&mut self) -> Result<(), EditorIoError>{
+        let entry = match self.history.pop_undo(){
+            Some(entry) => entry,
+            None => return Ok(()),
+        };
+
+        let mut reverted = Vec::new();
+        for effect in entry.applied.iter().rev(){
+            match self.revert(effect.clone()){
+                Ok(inverse) => reverted.push(inverse),
+                Err(err) => {
+                    //Apply again oldest first
+                    for inverse in reverted.into_iter().rev(){
+                        if let Err(reapply_err) = self.revert(inverse){
+                            eprintln!("Failed re-applying operation: {}", reapply_err);
+                        }
+                    }
+                    //Missing entries won't come back, keeping the operation would block older ones
+                    if matches!(err.get_type(), ErrorType::NotFound){
+                        eprintln!("Dropped {} from the history", entry.label);
                     }
+                    else{
+                        self.history.push_undo(entry);
+                    }
+                    return Err(err)
+                }
+            }
+        }
+        reverted.iter().for_each(|inverse| self.discard(inverse));
+        self.note_own_writes(&reverted);
+        self.unload_moved(&reverted);
+
+        println!("Undone: {}", entry.label);
+        self.history.push_redo(entry);
+        return Ok(())
+    }
+
+    ///Executes the last undone operation again.
+    pub(crate) fn redo(&mut self) -> Result<(), EditorIoError>{
+        let entry = match self.history.pop_redo(){
+            Some(entry) => entry,
+            None => return Ok(()),
+        };
+
+        let applied = self.apply_operation(entry.operation.clone())?;
+        self.commit(&applied)?;
+        self.note_own_writes(&applied);
+        self.unload_moved(&applied);
+
+        println!("Redone: {}", entry.label);
+        self.history.push_undo(HistoryEntry { 
+            applied: applied, 
+            ..entry 
+        });
+        return Ok(())
+    }
+
+    ///Applies an undo/redo command, reporting failures.
+    pub(crate) fn apply_history_command(&mut self, command: HistoryCommand){
+        let result = match command{
+            HistoryCommand::Undo => self.undo(),
+            HistoryCommand::Redo => self.redo(),
+        };
+        self.invalidate_pinned();
+
+        if let Err(err) = result{
+            eprintln!("Failed {:?}: {}", command, err);
+        }
+    }
+
+    pub(crate) fn get_history(&self) -> HistorySummary{
+        return self.history.get_summary()
+    }
+
+    ///Returns a short description of an operation, used as its history label.
+    fn describe(&self, operation: &UIDOperation) -> String{
+        let name = |id: &usize| self.flat_tree.get_path(*id)
+            .and_then(|path| path.file_name().map(|name| name.to_string_lossy().to_string()))
+            .unwrap_or_default();
+
+        return match operation{
+            UIDOperation::RENAME(id, new_name) => format!("Rename {} to {}", name(id), new_name),
+            UIDOperation::DELETE(id) => format!("Delete {}", name(id)),
+            UIDOperation::CREATE(_, new_name, _) => format!("Create {}", new_name),
+            UIDOperation::MOVE(id, _) => format!("Move {}", name(id)),
+            UIDOperation::COPY(id, _) => format!("Copy {}", name(id)),
+            UIDOperation::RESTORE(path) => format!("Restore {}", path.file_name().unwrap_or_default().to_string_lossy()),
+            UIDOperation::BATCH(operations) => match operations.as_slice(){
+                [operation] => self.describe(operation),
+                _ => format!("Batch of {} operations", operations.len()),
+            },
+        }
+    }
+
+    ///Applies an operation to the tree and on disk, returning its effects.
+    fn apply_operation(&mut self, operation: UIDOperation) -> Result<Vec<AppliedOperation>, EditorIoError>{
+        return match operation{
+            UIDOperation::RENAME(id, new_name) => self.rename_node(id, &new_name),
+            UIDOperation::DELETE(id) => self.delete_node(id),
+            UIDOperation::CREATE(parent_id, name, is_dir) => self.create_node(parent_id, &name, is_dir),
+            UIDOperation::MOVE(id, target_id) => self.move_node(id, target_id),
+            UIDOperation::COPY(id, target_id) => self.copy_node(id, target_id),
+            UIDOperation::RESTORE(trashed_path) => self.restore_node(&trashed_path),
+            UIDOperation::BATCH(operations) => self.apply_batch(operations),
+        }
+    }
+
+    ///Applies every operation of a compound operation. If any fails, the already applied ones
+    /// are reverted so that the batch succeeds or fails as one unit.
+    fn apply_batch(&mut self, operations: Vec<UIDOperation>) -> Result<Vec<AppliedOperation>, EditorIoError>{
+        let mut applied = Vec::new();
+
+        for operation in operations{
+            match self.apply_operation(operation){
+                Ok(effects) => applied.extend(effects),
+                Err(err) => {
+                    //Revert newest first
+                    for effect in applied.into_iter().rev(){
+                        match self.revert(effect){
+                            Ok(inverse) => self.discard(&inverse),
+                            Err(revert_err) => eprintln!("Failed reverting batch operation: {}", revert_err),
+                        }
+                    }
+                    return Err(err)
+                }
+            }
+        }
+
+        return Ok(applied)
+    }
+
+    ///Finalizes applied operations, recording deleted entries in the trash manifest.
+    /// If recording fails every effect is reverted, so that the operation fails as one unit.
+    fn commit(&mut self, applied: &[AppliedOperation]) -> Result<(), EditorIoError>{
+        let trashed: Vec<(PathBuf, PathBuf)> = applied
+            .iter()
+            .filter_map(|effect| match effect{
+                AppliedOperation::Deleted(path, _, trashed_path) => Some((path.clone(), trashed_path.clone())),
+                _ => None,
+            })
+            .collect();
+        if trashed.is_empty(){
+            return Ok(())
+        }
+
+        if let Err(err) = self.trash.record(&trashed){
+            //Revert newest first
+            for effect in applied.iter().rev(){
+                match self.revert(effect.clone()){
+                    Ok(inverse) => self.discard(&inverse),
+                    Err(revert_err) => eprintln!("Failed reverting operation: {}", revert_err),
+                }
+            }
+            return Err(err)
+        }
+
+        return Ok(())
+    }
+
+    ///Trashed entries, oldest first.
+    pub(crate) fn get_trash(&self) -> &Vec<TrashEntry>{
+        return self.trash.get_entries()
+    }
+
+    ///Permanently removes every trashed entry. Deletions whose trashed entries are gone can no longer be undone.
+    pub(crate) fn empty_trash(&mut self) -> Result<(), EditorIoError>{
+        let result = self.trash.empty();
+        self.history.retain_undo(|entry| entry.applied.iter().all(|effect| match effect{
+            AppliedOperation::Deleted(_, _, trashed_path) => trashed_path.symlink_metadata().is_ok(),
+            _ => true
+        }));
+
+        return result
+    }
+
+    ///Reverts the tree and disk effects of an applied operation. Returns the inverse effect,
+    /// reverting it applies the effect again. Created entries are kept in the trash until discarded.
+    fn revert(&mut self, effect: AppliedOperation) -> Result<AppliedOperation, EditorIoError>{
+        match effect{
+            AppliedOperation::Renamed(id, old_name) => {
+                let path = self.flat_tree.get_path(id)
+                    .ok_or(EditorIoError::new("Node not found", ErrorType::NotFound))?;
+                let new_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                io::rename_entry(&path, &path.with_file_name(&old_name))?;
+                self.flat_tree.rename(id, &old_name);
+
+                return Ok(AppliedOperation::Renamed(id, new_name))
+            },
+            AppliedOperation::Created(path) => {
+                let trashed_path = self.trash.move_to_trash(&path)?;
+                let nodes = self.flat_tree.remove_path(&path).unwrap_or_default();
+
+                return Ok(AppliedOperation::Deleted(path, nodes, trashed_path))
+            },
+            AppliedOperation::Moved(id, old_parent, old_path, new_path) => {
+                io::rename_entry(&new_path, &old_path)?;
+                let new_parent = self.flat_tree.get_parent_id(id);
+                let old_id = self.flat_tree.move_node(id, old_parent).unwrap_or(id);
+
+                return Ok(AppliedOperation::Moved(old_id, new_parent, new_path, old_path))
+            },
+            AppliedOperation::Deleted(path, nodes, trashed_path) => {
+                //Committed deletions are part of the trash manifest
+                let inverse = if self.trash.is_recorded(&trashed_path){
+                    AppliedOperation::Restored(self.trash.restore(&trashed_path)?)
+                }
+                else{
+                    io::rename_entry(&trashed_path, &path)?;
+                    AppliedOperation::Created(path)
+                };
+                self.flat_tree.restore(nodes);
+
+                return Ok(inverse)
+            },
+            AppliedOperation::Restored(entry) => {
+                io::rename_entry(&entry.original_path, &entry.trashed_path)?;
+                let nodes = self.flat_tree.remove_path(&entry.original_path).unwrap_or_default();
+                self.trash.record(&[(entry.original_path.clone(), entry.trashed_path.clone())])?;
+
+                return Ok(AppliedOperation::Deleted(entry.original_path, nodes, entry.trashed_path))
+            },
+        }
+    }
+
+    ///Permanently removes the entries a revert moved out of the way, once they can't be applied again.
+    fn discard(&self, inverse: &AppliedOperation){
+        if let AppliedOperation::Deleted(_, _, trashed_path) = inverse{
+            if self.trash.is_recorded(trashed_path){
+                return;
+            }
+            if let Err(err) = io::remove_entry(trashed_path){
+                eprintln!("Failed removing {}: {}", trashed_path.display(), err);
+            }
+        }
+    }
+
+    ///Renames a node in the tree and on disk.
+    fn rename_node(&mut self, id: usize, new_name: &String) -> Result<Vec<AppliedOperation>, EditorIoError>{
+        self.check_not_workspace_root(id)?;
+        let old_path = self.flat_tree.get_path(id)
+            .ok_or(EditorIoError::new("Node not found", ErrorType::NotFound))?;
+        let new_path = old_path.with_file_name(new_name);
+
+        //Unchanged name, nothing to do
+        if old_path.file_name().is_some_and(|name| name == new_name.as_str()){
+            return Ok(vec![])
+        }
+        let parent_id = self.flat_tree.get_parent_id(id);
+        self.flat_tree.validate_name(parent_id, new_name, Some(id))?;
+
+        if let Some(old_name) = self.flat_tree.rename(id, new_name){
+            if let Err(err) = io::rename_entry(&old_path, &new_path){
+                //Rollback tree node
+                self.flat_tree.rename(id, &old_name);
+                return Err(err)
+            }
+            println!("Renamed node: {} from {} to {}", id, old_name, new_name);
+            return Ok(vec![AppliedOperation::Renamed(id, old_name)])
+        }
+
+        return Ok(vec![])
+    }
+
+    ///Creates a file or directory on disk, inserts it under its parent node and expands the parent.
+    fn create_node(&mut self, parent_id: usize, name: &String, is_dir: bool) -> Result<Vec<AppliedOperation>, EditorIoError>{
+        let parent_path = self.flat_tree.get_path(parent_id)
+            .ok_or(EditorIoError::new("Node not found", ErrorType::NotFound))?;
+        self.flat_tree.validate_name(Some(parent_id), name, None)?;
+        let path = parent_path.join(name);
+
+        io::create_entry(&path, is_dir)?;
+        self.flat_tree.insert_path(&path)?;
+        self.flat_tree.expand(parent_id);
+
+        println!("Created node: {}", path.display());
+        return Ok(vec![AppliedOperation::Created(path)])
+    }
+
+    ///Workspace folders are managed from the settings, they can't be renamed, moved or deleted.
+    fn check_not_workspace_root(&self, id: usize) -> Result<(), EditorIoError>{
+        if self.flat_tree.is_workspace_root(id){
+            return Err(EditorIoError::new("Workspace folders can't be renamed, moved or deleted", ErrorType::InvalidInput))
+        }
+
+        return Ok(())
+    }
+
+    ///Returns the path of a target directory, where `None` is the tree root. Workspaces have no tree root.
+    fn get_target_path(&self, target_id: Option<usize>) -> Result<PathBuf, EditorIoError>{
+        return match target_id{
+            Some(target_id) => self.flat_tree.get_path(target_id)
+                .ok_or(EditorIoError::new("Node not found", ErrorType::NotFound)),
+            None => self.flat_tree.get_top_level()
+                .map(|root| root.to_path_buf())
+                .ok_or(EditorIoError::new("Target must be a folder of the workspace", ErrorType::InvalidInput)),
+        }
+    }
+
+    ///Copies a node and its subtree under a target directory on disk and in the tree, and expands the target.
+    /// Colliding names are resolved as "name (1).ext".
+    fn copy_node(&mut self, id: usize, target_id: Option<usize>) -> Result<Vec<AppliedOperation>, EditorIoError>{
+        let path = self.flat_tree.get_path(id)
+            .ok_or(EditorIoError::new("Node not found", ErrorType::NotFound))?;
+        let target_path = self.get_target_path(target_id)?;
+        let new_path = io::unique_path(&target_path.join(path.file_name().unwrap_or_default()));
+
+        io::copy_entry(&path, &new_path)?;
+        if let Err(err) = self.flat_tree.insert_path(&new_path){
+            //Rollback disk copy
+            let _ = io::remove_entry(&new_path);
+            return Err(err)
+        }
+        if let Some(target_id) = target_id{
+            self.flat_tree.expand(target_id);
+        }
+
+        println!("Copied node: {} to {}", path.display(), new_path.display());
+        return Ok(vec![AppliedOperation::Created(new_path)])
+    }
+
+    ///Moves a node under a target directory in the tree and on disk, and expands the target.
+    fn move_node(&mut self, id: usize, target_id: Option<usize>) -> Result<Vec<AppliedOperation>, EditorIoError>{
+        self.check_not_workspace_root(id)?;
+        let old_parent = self.flat_tree.get_parent_id(id);
+        //Already in target
+        if old_parent == target_id{
+            return Ok(vec![])
+        }
+
+        let old_path = self.flat_tree.get_path(id)
+            .ok_or(EditorIoError::new("Node not found", ErrorType::NotFound))?;
+        let target_path = self.get_target_path(target_id)?;
+        let name = old_path.file_name().unwrap_or_default();
+        let new_path = target_path.join(name);
+        //Checked before touching the tree, a rolled back move would lose the colliding node
+        self.flat_tree.validate_name(target_id, &name.to_string_lossy(), None)?;
+
+        let new_id = self.flat_tree.move_node(id, target_id)
+            .ok_or(EditorIoError::new("Invalid move target", ErrorType::InvalidInput))?;
+
+        if let Err(err) = io::rename_entry(&old_path, &new_path){
+            //Rollback tree nodes
+            self.flat_tree.move_node(new_id, old_parent);
+            return Err(err)
+        }
+        if let Some(target_id) = target_id{
+            self.flat_tree.expand(target_id);
+        }
+
+        println!("Moved node: {} to {}", old_path.display(), new_path.display());
+        return Ok(vec![AppliedOperation::Moved(new_id, old_parent, old_path, new_path)])
+    }
+
+    ///Removes a node from the tree and moves it into the trash, recorded once committed.
+    fn delete_node(&mut self, id: usize) -> Result<Vec<AppliedOperation>, EditorIoError>{
+        self.check_not_workspace_root(id)?;
+        if let Some(nodes) = self.flat_tree.remove(id){
+            let path = nodes[0].file_entry.path.clone();
+
+            match self.trash.move_to_trash(&path){
+                Ok(trashed_path) => {
+                    println!("Removed node: {}", path.display());
+                    return Ok(vec![AppliedOperation::Deleted(path, nodes, trashed_path)])
+                },
+                Err(err) => {
+                    //Rollback tree nodes
+                    self.flat_tree.restore(nodes);
+                    return Err(err)
                 },
             }
         }
+
+        return Ok(vec![])
+    }
+
+    ///Moves a trashed entry back to its original path, inserts it in the tree and reveals it.
+    fn restore_node(&mut self, trashed_path: &Path) -> Result<Vec<AppliedOperation>, EditorIoError>{
+        let entry = self.trash.restore(trashed_path)?;
+        if let Err(err) = self.flat_tree.insert_path(&entry.original_path){
+            //Rollback restore, partially inserted nodes are removed and the entry is trashed again
+            self.flat_tree.remove_path(&entry.original_path);
+            let rollback = io::rename_entry(&entry.original_path, &entry.trashed_path)
+                .and_then(|_| self.trash.record(&[(entry.original_path.clone(), entry.trashed_path.clone())]));
+            if let Err(rollback_err) = rollback{
+                eprintln!("Failed rolling back restore of {}: {}", entry.original_path.display(), rollback_err);
+            }
+            return Err(err)
+        }
+
+        if let Some(id) = self.flat_tree.find_by_path(&entry.original_path){
+            self.reveal(id);
+        }
+
+        println!("Restored node: {}", entry.original_path.display());
+        return Ok(vec![AppliedOperation::Restored(entry)])
     }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub(crate) enum UIDOperation{
     RENAME(usize, String),
-    DELETE(usize)
+    DELETE(usize),
+    //Parent id, name, is directory
+    CREATE(usize, String, bool),
+    //Node id, target directory id (None for the top level)
+    MOVE(usize, Option<usize>),
+    //Node id, target directory id (None for the top level)
+    COPY(usize, Option<usize>),
+    //Trashed path
+    RESTORE(PathBuf),
+    //Operations that succeed or fail as one unit
+    BATCH(Vec<UIDOperation>)
+}
+impl UIDOperation{
+    ///Ids of the nodes an operation acts on, the parent for *CREATE*.
+    pub(crate) fn get_ids(&self) -> Vec<usize>{
+        match self{
+            UIDOperation::RENAME(id, _) | UIDOperation::DELETE(id) | UIDOperation::CREATE(id, _, _) => return vec![*id],
+            UIDOperation::MOVE(id, _) | UIDOperation::COPY(id, _) => return vec![*id],
+            UIDOperation::RESTORE(_) => return Vec::new(),
+            UIDOperation::BATCH(operations) => return operations.iter().flat_map(|operation| operation.get_ids()).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum ClipboardMode{
+    Copy,
+    Cut
+}
+
+///Actions of the multi-selection context menu.
+#[derive(Debug, Clone, Copy)]
+enum SelectionAction{
+    Delete,
+    Clipboard(ClipboardMode)
 }
 
+///Drag and drop payload of a file tree node.
+#[derive(Debug, Clone, Copy)]
+struct DraggedNode(usize);
+
 ///Enum used mainly by the Layout-Menu handlers
 /// to communicate what panes needs reloading.
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Copy)]
@@ -150,12 +1262,36 @@ impl Pane{
         return &self.pane_type
     }
 
+    ///Directory of a file tree pane.
+    pub(crate) fn get_directory(&self) -> Option<&UiDirectory>{
+        match &self.pane_type{
+            PaneType::FileTree { directory, .. } => return Some(directory),
+            _ => return None,
+        }
+    }
+
+    ///Messages of a console pane.
+    pub(crate) fn get_messages_mut(&mut self) -> Option<&mut Vec<String>>{
+        match &mut self.pane_type{
+            PaneType::Console { messages, .. } => return Some(messages),
+            _ => return None,
+        }
+    }
+
+    ///Mutable directory of a file tree pane.
+    pub(crate) fn get_directory_mut(&mut self) -> Option<&mut UiDirectory>{
+        match &mut self.pane_type{
+            PaneType::FileTree { directory, .. } => return Some(directory),
+            _ => return None,
+        }
+    }
+
     ///Function that sets new settings from `Menu` by reloading pane and underlying components.
     pub(crate) fn reload_with_settings(&mut self, new_settings: FileTreeSettings){
         match &mut self.pane_type {
             PaneType::FileTree { directory, settings } => {
-                *settings = new_settings.clone();
-                directory.reload(new_settings);
+                *settings = new_settings;
+                directory.mark_dirty();
             },
             PaneType::Inspector { .. } => todo!(),
             PaneType::Console { .. } => todo!(),
@@ -180,156 +1316,690 @@ impl Pane{
             },
         }
     }
-}
+}
+
+
+
+pub(crate) struct TreeBehavior {}
+
+impl TreeBehavior{
+    fn render_file_tree(&mut self, ui: &mut egui::Ui, directory: &mut UiDirectory, settings: &mut FileTreeSettings) -> UiResponse{
+        let mut render_response = None;
+
+        //Render header and drag button
+        if let Some(dragged) = self.render_pane_header(ui, "File Tree"){
+            render_response = Some(dragged);
+        }
+
+        //Background build progress
+        if let Some(progress) = directory.get_build_progress(){
+            ui.horizontal(|ui| {
+                ui.add(egui::Spinner::new());
+                ui.weak(format!("Reading directories... {} read, {} left", progress.read, progress.queued));
+            });
+        }
+
+        //Open file finder
+        if ui.input(|input| input.modifiers.command && input.key_pressed(egui::Key::P)){
+            let mut finder = FileFinder::default();
+            finder.search(&directory.flat_tree, settings);
+            directory.finder = Some(finder);
+            //Directories not read yet are read to be searched
+            directory.mark_dirty();
+        }
+        if let Some(id) = self.render_file_finder(ui.ctx(), directory, settings){
+            directory.reveal(id);
+            directory.refresh(settings.clone());
+        }
+
+        //Render bookmarks
+        if let Some(id) = Self::render_pinned(ui, directory, settings){
+            directory.open_pinned(id);
+            directory.refresh(settings.clone());
+        }
+
+        //Render filter box
+        if Self::render_filter_bar(ui, &mut directory.filter){
+            directory.mark_dirty();
+            directory.refresh(settings.clone());
+        }
+
+        let is_filtering = directory.filter.is_active();
+        if is_filtering && directory.flat_tree.is_loading(){
+            Self::render_searching(ui);
+        }
+
+        //Render trash
+        if let Some(restore) = Self::render_trash(ui, directory){
+            directory.queue_operation(restore);
+        }
+
+        let mut toggled_dirs = Vec::new();
+        let mut ui_operations = Vec::new();
+        let mut clicked_nodes = Vec::new();
+        let mut toggled_pins = Vec::new();
+        //Dragged node, target directory, copy instead of move
+        let mut dropped_node = None;
+        let mut selection_action = None;
+        //Clipboard mode, node id, whole selection
+        let mut clipboard_request = None;
+        let mut paste_into = None;
+        let modifiers = ui.input(|input| input.modifiers);
+        let selection_len = directory.selection.len();
+        let clipboard_filled = directory.clipboard.is_some();
+        //Cut nodes are shown greyed out until pasted
+        let cut_nodes: HashSet<usize> = match &directory.clipboard{
+            Some((ClipboardMode::Cut, ids)) => ids.iter().copied().collect(),
+            _ => HashSet::new(),
+        };
+
+        //Selection, clipboard and history shortcuts, ignored while typing
+        if directory.finder.is_none() && ui.memory(|memory| memory.focused().is_none()){
+            if ui.input(|input| input.key_pressed(egui::Key::Escape)){
+                directory.clear_selection();
+            }
+            if Self::clipboard_shortcut(ui, egui::Key::C){
+                directory.set_clipboard(ClipboardMode::Copy, directory.selected_roots());
+            }
+            if Self::clipboard_shortcut(ui, egui::Key::X){
+                directory.set_clipboard(ClipboardMode::Cut, directory.selected_roots());
+            }
+            if Self::clipboard_shortcut(ui, egui::Key::V){
+                paste_into = Some(directory.paste_target());
+            }
+            //Redo first, as the undo shortcut also matches shifted presses
+            if ui.input_mut(|input| input.consume_key(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::Z)){
+                directory.apply_history_command(HistoryCommand::Redo);
+            }
+            else if ui.input_mut(|input| input.consume_key(egui::Modifiers::COMMAND, egui::Key::Z)){
+                directory.apply_history_command(HistoryCommand::Undo);
+            }
+        }
+        
+        let row_height = ui.spacing().interact_size.y;
+        let total_rows = directory.display_tree.len();
+        let mut scroll_area = egui::ScrollArea::vertical()
+            .max_width(f32::INFINITY)
+            .auto_shrink([false, true]);
+
+        //Scroll position of the last session
+        if let Some(offset) = directory.restore_scroll.take(){
+            scroll_area = scroll_area.vertical_scroll_offset(offset);
+        }
+        //Scroll to revealed node, rows outside the viewport don't exist so scroll by offset
+        if let Some(id) = directory.scroll_to.take(){
+            if let Some(index) = directory.display_tree.iter().position(|node| *node == id){
+                let row_span = row_height + ui.spacing().item_spacing.y;
+                let offset = index as f32 * row_span - ui.available_height() / 2.0;
+                scroll_area = scroll_area.vertical_scroll_offset(offset.max(0.0));
+            }
+        }
+        
+        let scroll_output = scroll_area.show_rows(ui, row_height, total_rows, |ui, row_range| {
+                //Only rows inside the viewport are laid out
+                let visible_items = directory.flat_tree.get_children_from_ids(&directory.display_tree[row_range]);
+                
+                for element in visible_items {
+                    let depth = element.depth;
+                    let indent_amount = depth * 20;
+
+                    let element_id = element.id.clone();
+                    let element_name = &element.file_entry.name;
+                    //While filtering, directories are shown expanded
+                    let is_expanded = element.expanded || is_filtering; 
+                    let is_ignored = element.file_entry.is_ignored || cut_nodes.contains(&element_id);
+                    let decoration = settings.decorations.get(&element.file_entry);
+                    let badges = directory.get_badges(element);
+                    let is_selected = directory.selection.contains(&element_id);
+                    let pin_text = if directory.is_pinned(element_id) { "Unpin" } else { "Pin" };
+                    //Context menus of a multi-selection member act on the whole selection
+                    let in_multi_selection = is_selected && selection_len > 1;
+                    
+                    ui.horizontal(|ui| {
+                        // Indentation based on depth
+                        ui.add_space(indent_amount as f32);
+                        
+                        if element.file_entry.is_dir {
+                            let expand_icon = if is_expanded {
+                                "▼"
+                            } else {
+                                "▶"
+                            };
+
+                            let dir_button = ui.add(
+                                egui::Button::new(Self::entry_text(ui, decoration, element_name, is_ignored))
+                                .frame(false)
+                                .selected(is_selected)
+                                .sense(egui::Sense::click_and_drag())
+                            );
+                            dir_button.dnd_set_drag_payload(DraggedNode(element_id));
+
+                            //Highlight and accept dropped nodes
+                            if let Some(dragged) = dir_button.dnd_hover_payload::<DraggedNode>(){
+                                if dragged.0 != element_id{
+                                    ui.painter().rect_stroke(
+                                        dir_button.rect, 
+                                        2.0, 
+                                        ui.visuals().selection.stroke, 
+                                        egui::StrokeKind::Outside
+                                    );
+                                }
+                            }
+                            if let Some(dropped) = dir_button.dnd_release_payload::<DraggedNode>(){
+                                if dropped.0 != element_id{
+                                    dropped_node = Some((dropped.0, element_id, modifiers.command));
+                                }
+                            }
+
+                            //Modifier clicks only change the selection
+                            let plain_click = dir_button.clicked() && !modifiers.command && !modifiers.shift;
+                            if dir_button.clicked(){
+                                clicked_nodes.push(element_id);
+                            }
+                            if (plain_click || ui.button(expand_icon).clicked()) && !is_filtering{
+                                toggled_dirs.push(element.id);
+                            }
+
+                            //Placeholder for directories being read
+                            if is_expanded && element.is_loading(){
+                                ui.add(egui::Spinner::new());
+                                ui.weak("Loading...");
+                            }
+
+                            dir_button.context_menu(|ui| {
+                                if in_multi_selection{
+                                    selection_action = Self::render_selection_menu(ui, selection_len).or(selection_action);
+                                    return;
+                                }
+                                //Directory new file operation
+                                ui.menu_button("New File", |ui| {
+                                    let validate = |name: &str| directory.flat_tree.validate_name(Some(element_id), name, None);
+                                    if let Some(name) = Self::render_name_input(ui, &mut directory.user_input, "File name", validate){
+                                        ui_operations.push(UIDOperation::CREATE(element_id, name, false));
+                                    }
+                                });
+                                //Directory new folder operation
+                                ui.menu_button("New Folder", |ui| {
+                                    let validate = |name: &str| directory.flat_tree.validate_name(Some(element_id), name, None);
+                                    if let Some(name) = Self::render_name_input(ui, &mut directory.user_input, "Folder name", validate){
+                                        ui_operations.push(UIDOperation::CREATE(element_id, name, true));
+                                    }
+                                });
+                                ui.separator();
+                                //Directory clipboard operations
+                                if let Some(mode) = Self::render_clipboard_menu(ui, clipboard_filled, &mut paste_into, Some(element_id)){
+                                    clipboard_request = Some((mode, element_id, false));
+                                }
+                                ui.separator();
+                                if ui.button(pin_text).clicked(){
+                                    toggled_pins.push(element_id);
+                                    ui.close_menu();
+                                }
+                                ui.separator();
+                                //Directory rename operation
+                                ui.menu_button("Rename", |ui| {
+                                    let validate = |name: &str| directory.flat_tree.validate_name(directory.flat_tree.get_parent_id(element_id), name, Some(element_id));
+                                    if let Some(name) = Self::render_name_input(ui, &mut directory.user_input, element_name, validate){
+                                        ui_operations.push(UIDOperation::RENAME(element_id, name));
+                                    }
+                                });
+                                //Directory delete operation
+                                if ui.button("Delete").clicked() {
+                                    ui_operations.push(UIDOperation::DELETE(element_id));
+                                    ui.close_menu();
+                                }
+                            });
+                            
+                        } 
+                        else {
+                            let file_button = ui.add(
+                                egui::Button::new(Self::entry_text(ui, decoration, element_name, is_ignored))
+                                .frame(false)
+                                .selected(is_selected)
+                                .sense(egui::Sense::click_and_drag())
+                            );
+                            file_button.dnd_set_drag_payload(DraggedNode(element_id));
+
+                            if file_button.clicked(){
+                                clicked_nodes.push(element_id);
+                            }
+
+                            file_button.context_menu(|ui| {
+                                if in_multi_selection{
+                                    selection_action = Self::render_selection_menu(ui, selection_len).or(selection_action);
+                                    return;
+                                }
+                                //File clipboard operations, pasting into the parent directory
+                                if let Some(mode) = Self::render_clipboard_menu(ui, clipboard_filled, &mut paste_into, directory.flat_tree.get_parent_id(element_id)){
+                                    clipboard_request = Some((mode, element_id, false));
+                                }
+                                ui.separator();
+                                if ui.button(pin_text).clicked(){
+                                    toggled_pins.push(element_id);
+                                    ui.close_menu();
+                                }
+                                ui.separator();
+                                //File rename operation
+                                ui.menu_button("Rename", |ui| {
+                                    let validate = |name: &str| directory.flat_tree.validate_name(directory.flat_tree.get_parent_id(element_id), name, Some(element_id));
+                                    if let Some(name) = Self::render_name_input(ui, &mut directory.user_input, element_name, validate){
+                                        ui_operations.push(UIDOperation::RENAME(element_id, name));
+                                    }
+                                });
+                                //File delete operation
+                                if ui.button("Delete").clicked() {
+                                    ui_operations.push(UIDOperation::DELETE(element_id));
+                                    ui.close_menu();
+                                }
+                            });
+                        }
+
+                        //Status badges
+                        for badge in badges{
+                            if let Some(badge_decoration) = settings.decorations.get_badge(&badge){
+                                let icon = egui::RichText::new(&badge_decoration.icon).small();
+                                let icon = match badge_decoration.get_color(){
+                                    Some(color) => icon.color(color),
+                                    None => icon,
+                                };
+                                ui.label(icon).on_hover_text(badge.get_tooltip());
+                            }
+                        }
+                    });   
+                }
+        });
+        directory.scroll_offset = scroll_output.state.offset.y;
+
+        for id in clicked_nodes{
+            directory.select(id, modifiers.command, modifiers.shift);
+        }
+        for id in toggled_pins{
+            directory.toggle_pin(id);
+        }
+        match selection_action{
+            Some(SelectionAction::Delete) => ui_operations.push(directory.batch_operation(UIDOperation::DELETE)),
+            Some(SelectionAction::Clipboard(mode)) => clipboard_request = Some((mode, 0, true)),
+            None => {},
+        }
+        if let Some((mode, id, whole_selection)) = clipboard_request{
+            let ids = if whole_selection { directory.selected_roots() } else { vec![id] };
+            directory.set_clipboard(mode, ids);
+        }
+        if let Some(target) = paste_into{
+            directory.paste(target);
+        }
+        if let Some((dragged, target, copy)) = dropped_node{
+            let operation = move |id| if copy { UIDOperation::COPY(id, Some(target)) } else { UIDOperation::MOVE(id, Some(target)) };
+
+            //Dragging a selected node drags the whole selection, which can't be dropped on one of its members
+            if directory.is_selected(dragged) && directory.selection.len() > 1{
+                if !directory.is_selected(target){
+                    ui_operations.push(directory.batch_operation(operation));
+                }
+            }
+            else{
+                ui_operations.push(operation(dragged));
+            }
+        }
+
+        if !toggled_dirs.is_empty(){
+            toggled_dirs.iter()
+                .for_each(|id| {
+                    directory.flat_tree.toggle_visibility(id);
+            });
+
+            //If even a single dir it toggled, the display tree has to be remade
+            directory.refresh(settings.clone());
+        }
+
+        //Queue directory operations
+        for op in ui_operations{
+            directory.queue_operation(op);
+        }
+        
+        //If tile was dragged, return response, else return None.
+        if render_response.is_some_and(|status| !matches!(status, egui_tiles::UiResponse::None)){
+            return render_response.unwrap()
+        }
+        return UiResponse::None
+    }
+
+    ///Renders the context menu of a multi-selection. Returns the action to apply to the whole selection.
+    fn render_selection_menu(ui: &mut egui::Ui, selection_len: usize) -> Option<SelectionAction>{
+        let mut action = None;
+
+        ui.weak(format!("{} items selected", selection_len));
+        ui.separator();
+
+        if ui.button("Copy").clicked(){
+            action = Some(SelectionAction::Clipboard(ClipboardMode::Copy));
+        }
+        if ui.button("Cut").clicked(){
+            action = Some(SelectionAction::Clipboard(ClipboardMode::Cut));
+        }
+        ui.separator();
+        if ui.button("Delete").clicked(){
+            action = Some(SelectionAction::Delete);
+        }
+
+        if action.is_some(){
+            ui.close_menu();
+        }
+        return action
+    }
+
+    ///Renders Copy, Cut and Paste menu entries. Returns the clipboard mode if Copy or Cut was clicked,
+    /// sets `paste_into` to `target_id` if Paste was clicked.
+    fn render_clipboard_menu(ui: &mut egui::Ui, clipboard_filled: bool, paste_into: &mut Option<Option<usize>>, target_id: Option<usize>) -> Option<ClipboardMode>{
+        let mut mode = None;
+
+        if ui.button("Copy").clicked(){
+            mode = Some(ClipboardMode::Copy);
+        }
+        if ui.button("Cut").clicked(){
+            mode = Some(ClipboardMode::Cut);
+        }
+        if ui.add_enabled(clipboard_filled, egui::Button::new("Paste")).clicked(){
+            *paste_into = Some(target_id);
+            ui.close_menu();
+        }
+
+        if mode.is_some(){
+            ui.close_menu();
+        }
+        return mode
+    }
+
+    ///Returns true if a clipboard shortcut (Ctrl + `key`) was pressed. Depending on the platform
+    /// these arrive as dedicated clipboard events instead of key presses.
+    fn clipboard_shortcut(ui: &egui::Ui, key: egui::Key) -> bool{
+        return ui.input(|input| input.events.iter().any(|event| match event{
+            egui::Event::Copy => key == egui::Key::C,
+            egui::Event::Cut => key == egui::Key::X,
+            egui::Event::Paste(_) => key == egui::Key::V,
+            egui::Event::Key { key: pressed_key, pressed: true, modifiers, .. } => *pressed_key == key && modifiers.command,
+            _ => false
+        }))
+    }
+
+    ///Renders the collapsible bookmarks section, if anything is pinned. Unpinning is applied immediately,
+    /// returns the node to open if an entry was clicked.
+    fn render_pinned(ui: &mut egui::Ui, directory: &mut UiDirectory, settings: &FileTreeSettings) -> Option<usize>{
+        let pinned = directory.get_pinned();
+        if pinned.is_empty(){
+            return None
+        }
+
+        let mut open = None;
+        let mut unpin = None;
+
+        egui::CollapsingHeader::new(format!("📌 Pinned ({})", pinned.len()))
+            .id_salt("file_tree_pinned")
+            .default_open(true)
+            .show(ui, |ui| {
+                for (id, entry) in pinned{
+                    let decoration = settings.decorations.get(&entry);
+
+                    ui.horizontal(|ui| {
+                        let button = ui.add(egui::Button::new(Self::entry_text(ui, decoration, &entry.name, false)).frame(false))
+                            .on_hover_text(directory.flat_tree.get_display_path(&entry.path).display().to_string());
+                        if button.clicked(){
+                            open = Some(id);
+                        }
+                        if ui.small_button("✖").on_hover_text("Unpin").clicked(){
+                            unpin = Some(id);
+                        }
+                    });
+                }
+            });
+
+        if let Some(id) = unpin{
+            directory.toggle_pin(id);
+        }
+        return open
+    }
+
+    ///Renders the collapsible trash section, if not empty. Emptying is applied immediately,
+    /// returns the restore operation if an entry was restored.
+    fn render_trash(ui: &mut egui::Ui, directory: &mut UiDirectory) -> Option<UIDOperation>{
+        if directory.get_trash().is_empty(){
+            return None
+        }
+
+        let mut restore = None;
+        let mut empty = false;
+
+        egui::CollapsingHeader::new(format!("🗑 Trash ({})", directory.get_trash().len()))
+            .id_salt("file_tree_trash")
+            .show(ui, |ui| {
+                egui::ScrollArea::vertical()
+                    .id_salt("file_tree_trash_entries")
+                    .max_height(150.0)
+                    .show(ui, |ui| {
+                        //Newest first
+                        for entry in directory.get_trash().iter().rev(){
+                            let path = directory.flat_tree.get_display_path(&entry.original_path);
+
+                            ui.horizontal(|ui| {
+                                if ui.small_button("Restore").clicked(){
+                                    restore = Some(UIDOperation::RESTORE(entry.trashed_path.clone()));
+                                }
+                                ui.label(path.display().to_string())
+                                    .on_hover_text(format!("Deleted {} ago", Self::format_elapsed(entry.deleted_at)));
+                            });
+                        }
+                    });
+
+                if ui.button("Empty trash").clicked(){
+                    empty = true;
+                }
+            });
+
+        if empty{
+            if let Err(err) = directory.empty_trash(){
+                eprintln!("Failed emptying trash: {}", err);
+            }
+        }
+        return restore
+    }
 
+    ///Formats the time elapsed since a UNIX timestamp (seconds), e.g. "5m".
+    fn format_elapsed(timestamp: u64) -> String{
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(timestamp);
+        let elapsed = now.saturating_sub(timestamp);
 
+        return match elapsed{
+            0..60 => format!("{}s", elapsed),
+            60..3600 => format!("{}m", elapsed / 60),
+            3600..86400 => format!("{}h", elapsed / 3600),
+            _ => format!("{}d", elapsed / 86400),
+        }
+    }
 
-pub(crate) struct TreeBehavior {}
+    ///Renders the filter text box and mode selection. Returns true if the filter changed.
+    fn render_filter_bar(ui: &mut egui::Ui, filter: &mut TreeFilter) -> bool{
+        let mut changed = false;
 
-impl TreeBehavior{
-    fn render_file_tree(&mut self, ui: &mut egui::Ui, directory: &mut UiDirectory, settings: &mut FileTreeSettings) -> UiResponse{
-        let mut render_response = None;
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("file_tree_filter_mode")
+                .selected_text(filter.mode.to_string())
+                .width(60.0)
+                .show_ui(ui, |ui| {
+                    for mode in [FilterMode::Substring, FilterMode::Glob, FilterMode::Regex]{
+                        changed |= ui.selectable_value(&mut filter.mode, mode, mode.to_string()).changed();
+                    }
+                });
 
-        //Render header and drag button
-        if let Some(dragged) = self.render_pane_header(ui, "File Tree"){
-            render_response = Some(dragged);
+            let clear_width = if filter.query.is_empty() { 0.0 } else { 24.0 };
+            let response = ui.add(egui::TextEdit::singleline(&mut filter.query)
+                .hint_text("Filter")
+                .desired_width(ui.available_width() - clear_width));
+            changed |= response.changed();
+
+            if !filter.query.is_empty() && ui.small_button("✖").clicked(){
+                filter.clear();
+                changed = true;
+            }
+        });
+
+        if changed{
+            filter.update();
         }
+        if let Some(err) = &filter.error{
+            ui.colored_label(ui.visuals().error_fg_color, err);
+        }
+        ui.separator();
 
-        let mut toggled_dirs = Vec::new();
-        let mut ui_operations = Vec::new();
-        
-        egui::ScrollArea::vertical()
-            .max_width(f32::INFINITY)
-            .auto_shrink([false, true])
-            .show(ui, |ui| {
-                //Get TreeNodes from Vec<id>
-                let sorted_display_tree = &directory.display_tree;
-                let visible_items = directory.flat_tree.get_children_from_ids(&sorted_display_tree);
-                
-                for element in visible_items {                        
-                    //Show element
-                    let show = settings.show_hidden_elements || (element.file_entry.metadata.file_attributes() & 0x2 == 0);
+        return changed
+    }
 
-                    if show{
-                        let depth = element.depth;
-                        let indent_amount = depth * 20;
+    ///Renders the placeholder shown while directories of a lazy tree are read to be searched.
+    fn render_searching(ui: &mut egui::Ui){
+        ui.horizontal(|ui| {
+            ui.add(egui::Spinner::new());
+            ui.weak("Searching unread directories...");
+        });
+    }
 
-                        let element_id = element.id.clone();
-                        let element_name = &element.file_entry.name;
-                        let is_expanded = element.expanded; 
-                        
-                        ui.horizontal(|ui| {
-                            // Indentation based on depth
-                            ui.add_space(indent_amount as f32);
-                            
-                            if element.file_entry.is_dir {
-                                let expand_icon = if is_expanded {
-                                    "▼"
-                                } else {
-                                    "▶"
-                                };
+    ///Renders the fuzzy file finder overlay. Returns the id of the selected node, if any.
+    fn render_file_finder(&mut self, ctx: &egui::Context, directory: &mut UiDirectory, settings: &FileTreeSettings) -> Option<usize>{
+        let finder = directory.finder.as_mut()?;
+        let tree = &directory.flat_tree;
 
-                                let dir_button = ui.add(
-                                    egui::Button::new(format!("📁 {}", element_name))
-                                    .frame(false)
-                                    .sense(egui::Sense::click())
-                                );
+        let mut selected = None;
+        let mut close = false;
 
-                                if dir_button.clicked() || ui.button(expand_icon).clicked(){
-                                    toggled_dirs.push(element.id);
-                                }
+        egui::Window::new("Find File")
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 40.0])
+            .show(ctx, |ui| {
+                let response = ui.add(egui::TextEdit::singleline(&mut finder.query)
+                    .hint_text("Search files...")
+                    .desired_width(400.0));
+                response.request_focus();
 
-                                dir_button.context_menu(|ui| {
-                                    //Directory rename operation
-                                    ui.menu_button("Rename", |ui| {
-                                        let mut user_buffer= directory.user_input.clone().unwrap_or(String::new());
-
-                                        let response = ui.add(egui::TextEdit::singleline(&mut user_buffer)
-                                            .hint_text(element_name));
-                                        
-                                        //Save changes in UIDirectory
-                                        if response.changed(){
-                                            directory.user_input = Some(user_buffer)
-                                        }
-                                        //If lost focus (enter pressed) push operation
-                                        else if response.lost_focus(){
-                                            ui_operations.push(UIDOperation::RENAME(element_id, directory.user_input.clone().unwrap_or(user_buffer)));
-                                            directory.user_input = None;
-                                            ui.close_menu();
-                                        }
-                                    });
-                                    //Directory delete operation
-                                    if ui.button("Delete").clicked() {
-                                        ui_operations.push(UIDOperation::DELETE(element_id));
-                                        ui.close_menu();
-                                    }
-                                });
-                                
-                            } 
-                            else {
-                                let file_button = ui.add(
-                                    egui::Button::new(format!("📄 {}", element_name))
-                                    .frame(false)
-                                    .sense(egui::Sense::click())
-                                );
-
-                                file_button.context_menu(|ui| {
-                                    //File rename operation
-                                    ui.menu_button("Rename", |ui| {
-                                        let mut user_buffer= directory.user_input.clone().unwrap_or(String::new());
-
-                                        let response = ui.add(egui::TextEdit::singleline(&mut user_buffer)
-                                            .hint_text(element_name));
-                                        
-                                        //Save changes in UIDirectory
-                                        if response.changed(){
-                                            directory.user_input = Some(user_buffer)
-                                        }
-                                        //If lost focus (enter pressed) push operation
-                                        else if response.lost_focus(){
-                                            ui_operations.push(UIDOperation::RENAME(element_id, directory.user_input.clone().unwrap_or(user_buffer)));
-                                            directory.user_input = None;
-                                            ui.close_menu();
-                                        }
-                                    });
-                                    //File delete operation
-                                    if ui.button("Delete").clicked() {
-                                        ui_operations.push(UIDOperation::DELETE(element_id));
-                                        ui.close_menu();
-                                    }
-                                });
-                            }
-                        });   
-                    }
-                    
+                if response.changed(){
+                    finder.search(tree, settings);
+                }
+                if tree.is_loading(){
+                    Self::render_searching(ui);
                 }
-        });
 
-        if !toggled_dirs.is_empty(){
-            toggled_dirs.iter()
-                .for_each(|id| {
-                    directory.flat_tree.toggle_visibility(id);
+                //Keyboard navigation
+                ui.input(|input| {
+                    if input.key_pressed(egui::Key::ArrowDown){
+                        finder.select_next();
+                    }
+                    if input.key_pressed(egui::Key::ArrowUp){
+                        finder.select_previous();
+                    }
+                    if input.key_pressed(egui::Key::Enter){
+                        selected = finder.get_selected();
+                    }
+                    if input.key_pressed(egui::Key::Escape){
+                        close = true;
+                    }
+                });
+
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        for (index, result) in finder.results.iter().enumerate(){
+                            let text = Self::highlight_match(ui, &result.path, &result.matched.indices);
+
+                            if ui.selectable_label(index == finder.selected, text).clicked(){
+                                selected = Some(result.id);
+                            }
+                        }
+                    });
             });
 
-            //If even a single dir it toggled, the display tree has to be remade
-            let new_sorted = directory.flat_tree.get_visible_items()
-                .iter()
-                .map(|entry| entry.id)
-                .collect();
+        if selected.is_some() || close{
+            directory.finder = None;
+        }
+
+        return selected
+    }
+
+    ///Creates a text layout where the matched characters are highlighted.
+    fn highlight_match(ui: &egui::Ui, text: &str, indices: &Vec<usize>) -> egui::text::LayoutJob{
+        let font_id = egui::TextStyle::Body.resolve(ui.style());
+        let normal = egui::TextFormat::simple(font_id.clone(), ui.visuals().text_color());
+        let highlighted = egui::TextFormat::simple(font_id, ui.visuals().hyperlink_color);
+
+        let mut job = egui::text::LayoutJob::default();
+        let mut buffer = [0; 4];
+
+        for (index, character) in text.chars().enumerate(){
+            let format = if indices.contains(&index){
+                highlighted.clone()
+            }
+            else{
+                normal.clone()
+            };
+            job.append(character.encode_utf8(&mut buffer), 0.0, format);
+        }
+
+        return job
+    }
 
-            directory.display_tree = new_sorted;
+    ///Text of a file tree row, its decorated icon followed by the name. Ignored entries are greyed out.
+    fn entry_text(ui: &egui::Ui, decoration: &Decoration, name: &str, is_ignored: bool) -> egui::text::LayoutJob{
+        let mut job = egui::text::LayoutJob::default();
+        let style = ui.style();
+
+        let mut icon = egui::RichText::new(format!("{} ", decoration.icon));
+        let mut text = egui::RichText::new(name);
+        if let (Some(color), false) = (decoration.get_color(), is_ignored){
+            icon = icon.color(color);
+        }
+        if is_ignored{
+            icon = icon.weak();
+            text = text.weak();
         }
 
-        //Queue directory operations
-        for op in ui_operations{
-            directory.queue_operation(op);
+        icon.append_to(&mut job, style, egui::FontSelection::Default, egui::Align::Center);
+        text.append_to(&mut job, style, egui::FontSelection::Default, egui::Align::Center);
+        return job
+    }
+
+    ///Renders a single line input used for naming entries, with the validation error shown below it.
+    /// Returns the user input once submitted (focus lost) and valid, and closes the menu.
+    fn render_name_input(ui: &mut egui::Ui, user_input: &mut Option<String>, hint: &str, validate: impl Fn(&str) -> Result<(), EditorIoError>) -> Option<String>{
+        let mut user_buffer = user_input.clone().unwrap_or_default();
+
+        let response = ui.add(egui::TextEdit::singleline(&mut user_buffer)
+            .hint_text(hint));
+
+        //Nothing typed yet is not reported
+        let validation = validate(&user_buffer);
+        if let (false, Err(err)) = (user_buffer.is_empty(), &validation){
+            //Broken naming rules are shown as is, other failures with their context
+            let message = match err.get_type(){
+                ErrorType::InvalidName(name_error) => name_error.to_string(),
+                _ => err.to_string(),
+            };
+            ui.colored_label(ui.visuals().error_fg_color, message);
         }
-        
-        //If tile was dragged, return response, else return None.
-        if render_response.is_some_and(|status| !matches!(status, egui_tiles::UiResponse::None)){
-            return render_response.unwrap()
+
+        //Save changes in UIDirectory
+        if response.changed(){
+            *user_input = Some(user_buffer);
         }
-        return UiResponse::None
+        //If lost focus (enter pressed) return input, invalid input keeps the menu open
+        else if response.lost_focus() && validation.is_ok(){
+            ui.close_menu();
+            return Some(user_input.take().unwrap_or(user_buffer))
+        }
+
+        return None
     }
 
     fn render_inspector(&mut self, ui: &mut egui::Ui, variables: &mut HashMap<String, String>, new_key: &mut String, new_value: &mut String) ->UiResponse{
@@ -481,9 +2151,11 @@ impl egui_tiles::Behavior<Pane> for TreeBehavior {
     fn pane_ui(&mut self, ui: &mut egui::Ui, _tile_id: egui_tiles::TileId, pane: &mut Pane) -> egui_tiles::UiResponse {
         match &mut pane.pane_type{
             PaneType::FileTree { directory, settings} => {
-                //Execute operations and reload directory (NOT PANE)
-                directory.execute_operations();
-                directory.reload(settings.clone());
+                //Apply external changes, execute operations and reload directory (NOT PANE) if any touched it
+                directory.sync_with_disk();
+                directory.sync_loads();
+                let _ = directory.execute_operations();
+                directory.refresh(settings.clone());
                 self.render_file_tree(ui, directory, settings)
             },
             PaneType::Inspector { variables, new_key, new_value } => {
@@ -499,28 +2171,44 @@ impl egui_tiles::Behavior<Pane> for TreeBehavior {
     }
 }
 
+///Creates a watcher of a root folder, None if watching failed.
+fn watch(root: &Path) -> Option<Rc<FileWatcher>>{
+    match FileWatcher::new(root){
+        Ok(watcher) => return Some(Rc::new(watcher)),
+        Err(err) => {
+            eprintln!("Error creating file watcher for {}: {}", root.display(), err);
+            return None
+        },
+    }
+}
+
+///Builds the file tree of the project and watches it. Workspace folders are mounted afterwards, see `UiDirectory::set_workspace_roots`.
+fn create_directory(settings: &EditorSettings, ids: NodeIds) -> Result<UiDirectory, EditorIoError>{
+    //Without an event loop (e.g. in tests) the tree is built right away
+    let background = USER_EVENT_PROXY.get().is_some();
+    let (tree, pending) = TreeBuilder::build_workspace(settings, &[], ids, background)?;
+
+    let root = tree.get_root().to_path_buf();
+    let watchers = watch(&root).into_iter().collect();
+
+    let mut directory = UiDirectory::new(tree, watchers);
+    directory.start_build(&root, pending.into_iter().next());
+
+    return Ok(directory)
+}
+
 ///Entry point for initializing and retrieving the Layout tree.
 pub(crate) fn create_tree(settings: EditorSettings) -> Result<egui_tiles::Tree<Pane>, EditorIoError> {
     let mut tiles = Tiles::default();
 
-    let mut tree_builder = TreeBuilder::init(None)?;
-    let _ = tree_builder.build()?;
-
-    let tree = tree_builder.get_tree();
-    
-    let visible = tree.get_visible_items()
-        .iter()
-        .map(|entry| entry.id)
-        .collect();
-    
-    let directory: UiDirectory = {
-        UiDirectory { 
-            flat_tree: tree,
-            display_tree: visible,
-            operations: Vec::new(),
-            user_input: None
-        }
-    };
+    let mut directory = create_directory(&settings, NodeIds::new())?;
+    match settings::load_project(directory.get_root()){
+        Ok(project) => {
+            directory.set_workspace_roots(&project.workspace_roots, &settings);
+            directory.restore_state(project.tree_state);
+        },
+        Err(err) => eprintln!("Failed loading project file: {}", err),
+    }
 
     let file_tree = tiles.insert_pane(Pane::new(
         0,
@@ -575,4 +2263,962 @@ pub(crate) fn create_tree(settings: EditorSettings) -> Result<egui_tiles::Tree<P
     let root = tiles.insert_vertical_tile(vec![middle_section, bottom_section]);
     
     Ok(Tree::new("main_layout", root, tiles))
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{error::NameError, rules::{EntryRules, SymlinkPolicy}};
+    use std::{env, fs};
+
+    //Runs the per frame directory work of `pane_ui`, returns true if the display tree was recomputed.
+    fn run_frame(directory: &mut UiDirectory, settings: &FileTreeSettings) -> bool{
+        directory.sync_with_disk();
+        directory.sync_loads();
+        let _ = directory.execute_operations();
+        return directory.refresh(settings.clone())
+    }
+
+    /* 
+        Runs frames over a directory and validates that the display tree is
+        only recomputed after an expansion, an operation or a settings change,
+        and never on idle frames.
+    */
+    #[test]
+    fn test_idle_frames_do_not_reload() {
+        let test_dir = env::temp_dir().join("reasy_test_idle_frames");
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(test_dir.join("a")).unwrap();
+        fs::write(test_dir.join("a").join("file1.txt"), "test1").unwrap();
+
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &EditorSettings::default()).unwrap();
+        let _ = builder.build();
+        let mut directory = UiDirectory::new(builder.get_tree(), Vec::new());
+        let settings = FileTreeSettings::from(EditorSettings::default());
+
+        //First frame builds the display tree
+        assert!(run_frame(&mut directory, &settings));
+        assert_eq!(directory.display_tree.len(), 1);
+        assert!(!run_frame(&mut directory, &settings));
+        assert!(!run_frame(&mut directory, &settings));
+
+        //Expansion
+        let dir_id = directory.flat_tree.find_by_path(&test_dir.join("a")).unwrap();
+        directory.flat_tree.toggle_visibility(&dir_id);
+        assert!(run_frame(&mut directory, &settings));
+        assert_eq!(directory.display_tree.len(), 2);
+        assert!(!run_frame(&mut directory, &settings));
+
+        //Operation
+        directory.queue_operation(UIDOperation::CREATE(dir_id, "file2.txt".to_string(), false));
+        assert!(run_frame(&mut directory, &settings));
+        assert_eq!(directory.display_tree.len(), 3);
+        assert!(!run_frame(&mut directory, &settings));
+
+        //Settings change
+        directory.mark_dirty();
+        assert!(run_frame(&mut directory, &settings));
+        assert!(!run_frame(&mut directory, &settings));
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    /* 
+        Selects nodes with toggle and range clicks and validates that batch
+            1. Moves are reverted as a unit when one of them fails
+            2. Copies and deletes are applied to every selected node
+            3. Operations are reverted as a unit when deletions fail to be recorded in the trash
+    */
+    #[test]
+    fn test_batch_operations() {
+        let test_dir = env::temp_dir().join("reasy_test_batch_operations");
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(test_dir.join("a")).unwrap();
+        fs::create_dir_all(test_dir.join("b")).unwrap();
+        fs::write(test_dir.join("f1.txt"), "test1").unwrap();
+        fs::write(test_dir.join("f2.txt"), "test2").unwrap();
+        fs::write(test_dir.join("b").join("f2.txt"), "collision").unwrap();
+
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &EditorSettings::default()).unwrap();
+        let _ = builder.build();
+        let mut directory = UiDirectory::new(builder.get_tree(), Vec::new());
+        let settings = FileTreeSettings::from(EditorSettings::default());
+        run_frame(&mut directory, &settings);
+
+        let a_id = directory.flat_tree.find_by_path(&test_dir.join("a")).unwrap();
+        let b_id = directory.flat_tree.find_by_path(&test_dir.join("b")).unwrap();
+        let f1_id = directory.flat_tree.find_by_path(&test_dir.join("f1.txt")).unwrap();
+        let f2_id = directory.flat_tree.find_by_path(&test_dir.join("f2.txt")).unwrap();
+
+        directory.select(f1_id, false, false);
+        directory.select(f2_id, true, false);
+        assert_eq!(directory.selected_roots(), vec![f1_id, f2_id]);
+
+        //Second move collides, first one is reverted
+        let operation = directory.batch_operation(|id| UIDOperation::MOVE(id, Some(b_id)));
+        directory.queue_operation(operation);
+        assert_eq!(directory.execute_operations().len(), 1);
+        assert!(test_dir.join("f1.txt").exists());
+        assert!(!test_dir.join("b").join("f1.txt").exists());
+        assert_eq!(directory.flat_tree.find_by_path(&test_dir.join("f1.txt")), Some(f1_id));
+        assert!(directory.flat_tree.find_by_path(&test_dir.join("b").join("f1.txt")).is_none());
+
+        //Range selection
+        directory.select(a_id, false, false);
+        directory.select(f2_id, false, true);
+        assert_eq!(directory.selected_roots(), vec![a_id, b_id, f1_id, f2_id]);
+        directory.select(f1_id, false, false);
+        directory.select(f2_id, false, true);
+        assert_eq!(directory.selected_roots(), vec![f1_id, f2_id]);
+
+        let operation = directory.batch_operation(|id| UIDOperation::COPY(id, Some(a_id)));
+        directory.queue_operation(operation);
+        assert!(directory.execute_operations().is_empty());
+        assert_eq!(fs::read_to_string(test_dir.join("a").join("f2.txt")).unwrap(), "test2");
+        assert!(directory.flat_tree.find_by_path(&test_dir.join("a").join("f1.txt")).is_some());
+
+        let operation = directory.batch_operation(UIDOperation::DELETE);
+        directory.queue_operation(operation);
+        assert!(directory.execute_operations().is_empty());
+        assert!(directory.flat_tree.find_by_path(&test_dir.join("f1.txt")).is_none());
+        assert!(directory.flat_tree.find_by_path(&test_dir.join("f2.txt")).is_none());
+        //Deleted entries are moved into the trash
+        assert_eq!(directory.get_trash().len(), 2);
+        assert!(!test_dir.join("f1.txt").exists());
+
+        //Trash manifest can't be written, the whole batch is reverted
+        let manifest = test_dir.join(crate::utils::trash::TRASH_DIR).join("manifest.json");
+        fs::remove_file(&manifest).unwrap();
+        fs::create_dir(&manifest).unwrap();
+        let copied_f1 = directory.flat_tree.find_by_path(&test_dir.join("a").join("f1.txt")).unwrap();
+        let copied_f2 = directory.flat_tree.find_by_path(&test_dir.join("a").join("f2.txt")).unwrap();
+        let history_len = directory.get_history().undo.len();
+        directory.queue_operation(UIDOperation::BATCH(vec![
+            UIDOperation::RENAME(copied_f2, "g2.txt".to_string()),
+            UIDOperation::DELETE(copied_f1)
+        ]));
+        assert_eq!(directory.execute_operations().len(), 1);
+        assert!(test_dir.join("a").join("f1.txt").exists());
+        assert!(test_dir.join("a").join("f2.txt").exists());
+        assert!(!test_dir.join("a").join("g2.txt").exists());
+        assert_eq!(directory.flat_tree.find_by_path(&test_dir.join("a").join("f1.txt")), Some(copied_f1));
+        assert_eq!(directory.flat_tree.find_by_path(&test_dir.join("a").join("f2.txt")), Some(copied_f2));
+        assert_eq!(directory.get_trash().len(), 2);
+        assert_eq!(directory.get_history().undo.len(), history_len);
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    /* 
+        Copies, cuts and pastes nodes and validates that
+            1. Colliding names are resolved as "name (n).ext"
+            2. Pasted subtrees are inserted with fresh ids
+            3. Cut nodes are moved and can only be pasted once
+    */
+    #[test]
+    fn test_clipboard() {
+        let test_dir = env::temp_dir().join("reasy_test_clipboard");
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(test_dir.join("a")).unwrap();
+        fs::create_dir_all(test_dir.join("b")).unwrap();
+        fs::write(test_dir.join("a").join("inner.txt"), "inner").unwrap();
+        fs::write(test_dir.join("f1.txt"), "test1").unwrap();
+
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &EditorSettings::default()).unwrap();
+        let _ = builder.build();
+        let mut directory = UiDirectory::new(builder.get_tree(), Vec::new());
+        let settings = FileTreeSettings::from(EditorSettings::default());
+        run_frame(&mut directory, &settings);
+
+        let a_id = directory.flat_tree.find_by_path(&test_dir.join("a")).unwrap();
+        let b_id = directory.flat_tree.find_by_path(&test_dir.join("b")).unwrap();
+        let f1_id = directory.flat_tree.find_by_path(&test_dir.join("f1.txt")).unwrap();
+        let inner_id = directory.flat_tree.find_by_path(&test_dir.join("a").join("inner.txt")).unwrap();
+
+        //Paste twice in the same directory
+        directory.set_clipboard(ClipboardMode::Copy, vec![f1_id, a_id]);
+        directory.paste(None);
+        assert!(run_frame(&mut directory, &settings));
+        directory.paste(None);
+        assert!(run_frame(&mut directory, &settings));
+        assert_eq!(fs::read_to_string(test_dir.join("f1 (1).txt")).unwrap(), "test1");
+        assert!(test_dir.join("f1 (2).txt").exists());
+        assert!(test_dir.join("a (2)").join("inner.txt").exists());
+
+        let copied_id = directory.flat_tree.find_by_path(&test_dir.join("a (1)").join("inner.txt")).unwrap();
+        assert_ne!(copied_id, inner_id);
+        assert!(directory.flat_tree.find_by_path(&test_dir.join("f1 (1).txt")).is_some());
+
+        //Cut into a directory
+        directory.set_clipboard(ClipboardMode::Cut, vec![f1_id]);
+        directory.select(inner_id, false, false);
+        assert_eq!(directory.paste_target(), Some(a_id));
+        directory.paste(Some(b_id));
+        assert!(run_frame(&mut directory, &settings));
+        assert!(!test_dir.join("f1.txt").exists());
+        assert!(directory.flat_tree.find_by_path(&test_dir.join("b").join("f1.txt")).is_some());
+        assert!(directory.clipboard.is_none());
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    /* 
+        Deletes nodes and validates that
+            1. Entries are moved into the ignored trash directory and recorded in its manifest
+            2. Restored entries return to their original path and tree position
+            3. Failed restores leave the entries in the trash
+            4. Emptying the trash removes the entries permanently
+    */
+    #[test]
+    fn test_trash() {
+        let test_dir = env::temp_dir().join("reasy_test_trash");
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(test_dir.join("a")).unwrap();
+        fs::write(test_dir.join("a").join("f1.txt"), "test1").unwrap();
+        fs::write(test_dir.join("f2.txt"), "test2").unwrap();
+
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &EditorSettings::default()).unwrap();
+        let _ = builder.build();
+        let mut directory = UiDirectory::new(builder.get_tree(), Vec::new());
+        let settings = FileTreeSettings::from(EditorSettings::default());
+        run_frame(&mut directory, &settings);
+
+        let f1_id = directory.flat_tree.find_by_path(&test_dir.join("a").join("f1.txt")).unwrap();
+        let f2_id = directory.flat_tree.find_by_path(&test_dir.join("f2.txt")).unwrap();
+        directory.queue_operation(UIDOperation::DELETE(f1_id));
+        directory.queue_operation(UIDOperation::DELETE(f2_id));
+        assert!(directory.execute_operations().is_empty());
+
+        let trash_dir = test_dir.join(crate::utils::trash::TRASH_DIR);
+        assert!(directory.flat_tree.get_rules(&trash_dir).is_ignored(&trash_dir, true));
+        assert!(!test_dir.join("a").join("f1.txt").exists());
+        assert_eq!(fs::read_to_string(trash_dir.join("f1.txt")).unwrap(), "test1");
+        assert_eq!(Trash::open(&test_dir).get_entries(), directory.get_trash());
+
+        //Restore
+        let trashed_path = trash_dir.join("f1.txt");
+        directory.queue_operation(UIDOperation::RESTORE(trashed_path));
+        assert!(directory.execute_operations().is_empty());
+        assert_eq!(fs::read_to_string(test_dir.join("a").join("f1.txt")).unwrap(), "test1");
+        assert_eq!(directory.flat_tree.find_by_path(&test_dir.join("a").join("f1.txt")), Some(f1_id));
+        assert_eq!(directory.get_trash().len(), 1);
+
+        //Trash manifest can't be written, the failed restore keeps the entry trashed
+        let manifest = trash_dir.join("manifest.json");
+        fs::remove_file(&manifest).unwrap();
+        fs::create_dir(&manifest).unwrap();
+        directory.queue_operation(UIDOperation::RESTORE(trash_dir.join("f2.txt")));
+        assert_eq!(directory.execute_operations().len(), 1);
+        assert!(trash_dir.join("f2.txt").exists() && !test_dir.join("f2.txt").exists());
+        assert!(directory.flat_tree.find_by_path(&test_dir.join("f2.txt")).is_none());
+        assert_eq!(directory.get_trash().len(), 1);
+        fs::remove_dir(&manifest).unwrap();
+
+        //Empty
+        directory.empty_trash().unwrap();
+        assert!(directory.get_trash().is_empty());
+        assert!(!trash_dir.join("f2.txt").exists());
+        assert!(Trash::open(&test_dir).get_entries().is_empty());
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    /* 
+        Executes operations and validates that
+            1. Undo reverts them on disk and in the tree, including trash deletions
+            2. Redo executes them again, and new operations clear the redo stack
+            3. A failed undo applies the reverted effects again and stays undoable
+            4. The history is bounded
+            5. Emptying the trash drops the deletions from the history, and operations on
+               entries gone for good are dropped when undone, so older ones stay undoable
+    */
+    #[test]
+    fn test_undo_redo() {
+        let test_dir = env::temp_dir().join("reasy_test_undo_redo");
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(test_dir.join("a")).unwrap();
+        fs::write(test_dir.join("f1.txt"), "test1").unwrap();
+
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &EditorSettings::default()).unwrap();
+        let _ = builder.build();
+        let mut directory = UiDirectory::new(builder.get_tree(), Vec::new());
+        let settings = FileTreeSettings::from(EditorSettings::default());
+        run_frame(&mut directory, &settings);
+
+        let a_id = directory.flat_tree.find_by_path(&test_dir.join("a")).unwrap();
+        let f1_id = directory.flat_tree.find_by_path(&test_dir.join("f1.txt")).unwrap();
+
+        //Rename
+        directory.queue_operation(UIDOperation::RENAME(f1_id, "f2.txt".to_string()));
+        assert!(run_frame(&mut directory, &settings));
+        assert_eq!(directory.get_history().undo, vec!["Rename f1.txt to f2.txt"]);
+        directory.undo().unwrap();
+        assert!(test_dir.join("f1.txt").exists() && !test_dir.join("f2.txt").exists());
+        assert_eq!(directory.flat_tree.get_path(f1_id), Some(test_dir.join("f1.txt")));
+        directory.redo().unwrap();
+        assert!(test_dir.join("f2.txt").exists());
+        directory.undo().unwrap();
+
+        //Move
+        directory.queue_operation(UIDOperation::MOVE(f1_id, Some(a_id)));
+        run_frame(&mut directory, &settings);
+        assert!(test_dir.join("a").join("f1.txt").exists());
+        directory.undo().unwrap();
+        assert!(test_dir.join("f1.txt").exists());
+        assert_eq!(directory.flat_tree.get_parent_id(f1_id), None);
+        //Recording the move cleared the undone rename
+        assert_eq!(directory.get_history().redo, vec!["Move f1.txt"]);
+
+        //Delete
+        directory.queue_operation(UIDOperation::DELETE(f1_id));
+        run_frame(&mut directory, &settings);
+        assert!(directory.get_history().redo.is_empty());
+        assert_eq!(directory.get_trash().len(), 1);
+        directory.undo().unwrap();
+        assert_eq!(fs::read_to_string(test_dir.join("f1.txt")).unwrap(), "test1");
+        assert!(directory.get_trash().is_empty());
+        assert!(directory.flat_tree.find_by_path(&test_dir.join("f1.txt")).is_some());
+
+        //Create
+        directory.queue_operation(UIDOperation::CREATE(a_id, "new".to_string(), true));
+        run_frame(&mut directory, &settings);
+        directory.undo().unwrap();
+        assert!(!test_dir.join("a").join("new").exists());
+        assert!(directory.flat_tree.find_by_path(&test_dir.join("a").join("new")).is_none());
+        let trash_dir = test_dir.join(crate::utils::trash::TRASH_DIR);
+        assert!(!trash_dir.join("new").exists());
+
+        //Failed revert of the second effect, the first one is applied again
+        directory.queue_operation(UIDOperation::BATCH(vec![
+            UIDOperation::RENAME(f1_id, "g1.txt".to_string()),
+            UIDOperation::CREATE(a_id, "new.txt".to_string(), false),
+        ]));
+        run_frame(&mut directory, &settings);
+        fs::write(test_dir.join("f1.txt"), "blocker").unwrap();
+        assert!(directory.undo().is_err());
+        assert!(test_dir.join("a").join("new.txt").exists());
+        assert!(directory.flat_tree.find_by_path(&test_dir.join("a").join("new.txt")).is_some());
+        assert_eq!(directory.flat_tree.get_path(f1_id), Some(test_dir.join("g1.txt")));
+        assert_eq!(directory.get_history().undo[0], "Batch of 2 operations");
+        fs::remove_file(test_dir.join("f1.txt")).unwrap();
+        directory.undo().unwrap();
+        assert!(test_dir.join("f1.txt").exists() && !test_dir.join("a").join("new.txt").exists());
+        assert!(!trash_dir.join("new.txt").exists());
+
+        //Bounded history
+        directory.history = OperationHistory::new(2);
+        for name in ["b", "c", "d"]{
+            directory.queue_operation(UIDOperation::CREATE(a_id, name.to_string(), false));
+            run_frame(&mut directory, &settings);
+        }
+        assert_eq!(directory.get_history().undo, vec!["Create d", "Create c"]);
+
+        //Emptied trash
+        directory.history = OperationHistory::new(HISTORY_LIMIT);
+        directory.queue_operation(UIDOperation::RENAME(f1_id, "h1.txt".to_string()));
+        run_frame(&mut directory, &settings);
+        directory.queue_operation(UIDOperation::DELETE(a_id));
+        run_frame(&mut directory, &settings);
+        directory.empty_trash().unwrap();
+        assert_eq!(directory.get_history().undo, vec!["Rename f1.txt to h1.txt"]);
+        directory.undo().unwrap();
+        assert!(test_dir.join("f1.txt").exists() && !test_dir.join("a").exists());
+
+        //Renamed, then deleted for good
+        directory.queue_operation(UIDOperation::RENAME(f1_id, "h1.txt".to_string()));
+        run_frame(&mut directory, &settings);
+        directory.queue_operation(UIDOperation::DELETE(f1_id));
+        run_frame(&mut directory, &settings);
+        directory.empty_trash().unwrap();
+        assert!(directory.undo().is_err());
+        assert!(directory.get_history().undo.is_empty());
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    /* 
+        Validates that invalid names are rejected with a typed error before touching the disk:
+            1. Empty, reserved and invalid character names
+            2. Sibling collisions, while renaming a node to its own name is a no-op
+    */
+    #[test]
+    fn test_name_validation() {
+        let test_dir = env::temp_dir().join("reasy_test_name_validation");
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(test_dir.join("a")).unwrap();
+        fs::write(test_dir.join("f1.txt"), "test1").unwrap();
+        fs::write(test_dir.join("a").join("f2.txt"), "test2").unwrap();
+
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &EditorSettings::default()).unwrap();
+        let _ = builder.build();
+        let mut directory = UiDirectory::new(builder.get_tree(), Vec::new());
+        let settings = FileTreeSettings::from(EditorSettings::default());
+        run_frame(&mut directory, &settings);
+
+        let a_id = directory.flat_tree.find_by_path(&test_dir.join("a")).unwrap();
+        let f1_id = directory.flat_tree.find_by_path(&test_dir.join("f1.txt")).unwrap();
+
+        for (name, expected) in [
+            ("", NameError::Empty),
+            ("  ", NameError::Empty),
+            ("..", NameError::Reserved),
+            ("x/y", NameError::InvalidCharacter('/')),
+            ("a", NameError::Collision("a".to_string()))
+        ]{
+            directory.queue_operation(UIDOperation::RENAME(f1_id, name.to_string()));
+            let errors = directory.execute_operations();
+            assert_eq!(errors.len(), 1);
+            assert!(matches!(errors[0].get_type(), ErrorType::InvalidName(err) if *err == expected), "{}", name);
+        }
+        assert!(test_dir.join("f1.txt").exists());
+
+        //Collisions are checked within the target directory only
+        assert!(directory.flat_tree.validate_name(Some(a_id), "f2.txt", None).is_err());
+        assert!(directory.flat_tree.validate_name(None, "f2.txt", None).is_ok());
+        directory.queue_operation(UIDOperation::CREATE(a_id, "f2.txt".to_string(), false));
+        assert_eq!(directory.execute_operations().len(), 1);
+
+        //Unchanged name
+        directory.queue_operation(UIDOperation::RENAME(f1_id, "f1.txt".to_string()));
+        assert!(directory.execute_operations().is_empty());
+        assert!(directory.get_history().undo.is_empty());
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    /* 
+        Saves the state of an expanded tree and restores it on a new lazily built tree, validating that
+            1. Nested directories are expanded once their parents are read
+            2. Selection and scroll offset are restored, missing paths are dropped
+            3. States saved while restoring keep the parts not restored yet
+    */
+    #[test]
+    fn test_restore_state() {
+        let test_dir = env::temp_dir().join("reasy_test_restore_state");
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(test_dir.join("a").join("nested")).unwrap();
+        fs::create_dir_all(test_dir.join("b")).unwrap();
+        fs::write(test_dir.join("a").join("nested").join("file1.txt"), "test1").unwrap();
+
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &EditorSettings::default()).unwrap();
+        let _ = builder.build();
+        let mut directory = UiDirectory::new(builder.get_tree(), Vec::new());
+        let settings = FileTreeSettings::from(EditorSettings::default());
+        run_frame(&mut directory, &settings);
+
+        let file_path = test_dir.join("a").join("nested").join("file1.txt");
+        let file_id = directory.flat_tree.find_by_path(&file_path).unwrap();
+        directory.reveal(file_id);
+        directory.select(file_id, false, false);
+        directory.scroll_offset = 12.0;
+
+        let mut state = directory.get_state();
+        assert_eq!(state.expanded, vec![PathBuf::from("a"), PathBuf::from("a").join("nested")]);
+        assert_eq!(state.selection, vec![PathBuf::from("a").join("nested").join("file1.txt")]);
+        state.expanded.push(PathBuf::from("missing"));
+
+        //New session, directories are read on expansion
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &EditorSettings::default()).unwrap();
+        builder.build_lazy();
+        let mut directory = UiDirectory::new(builder.get_tree(), Vec::new());
+        directory.restore_state(state.clone());
+        //Saving before the restore finishes keeps the pending parts
+        let pending = directory.get_state();
+        assert!(pending.expanded.contains(&PathBuf::from("missing")));
+        assert_eq!((pending.selection, pending.scroll_offset), (state.selection, 12.0));
+
+        for _ in 0..200{
+            run_frame(&mut directory, &settings);
+            if directory.restoring.is_none(){
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        run_frame(&mut directory, &settings);
+
+        assert!(directory.restoring.is_none());
+        assert_eq!(directory.restore_scroll, Some(12.0));
+        let file_id = directory.flat_tree.find_by_path(&file_path).unwrap();
+        assert!(directory.is_selected(file_id));
+        assert!(directory.display_tree.contains(&file_id));
+        assert_eq!(directory.display_tree.len(), 4);
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    /* 
+        Validates the status badges of nodes:
+            1. Ignored entries
+            2. Failed operations, cleared by a later successful one
+            3. Modified files reported by the watcher, unless written by the editor
+            4. Entries whose metadata couldn't be read keep their file type, are flagged and reported
+    */
+    #[test]
+    fn test_badges() {
+        let test_dir = env::temp_dir().join("reasy_test_badges");
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(test_dir.join("a")).unwrap();
+        fs::write(test_dir.join("f1.txt"), "test1").unwrap();
+        fs::write(test_dir.join("a").join("f1.txt"), "test1").unwrap();
+        fs::write(test_dir.join(".ignore"), "ignored.txt").unwrap();
+        fs::write(test_dir.join("ignored.txt"), "").unwrap();
+
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &EditorSettings::default()).unwrap();
+        let _ = builder.build();
+        let watchers = FileWatcher::new(&test_dir).ok().map(Rc::new).into_iter().collect();
+        let mut directory = UiDirectory::new(builder.get_tree(), watchers);
+        let settings = FileTreeSettings::from(EditorSettings::default());
+        run_frame(&mut directory, &settings);
+
+        let badges = |directory: &UiDirectory, path: &Path| {
+            let id = directory.flat_tree.find_by_path(path).unwrap();
+            let node = directory.flat_tree.get_children_from_ids(&[id])[0];
+            directory.get_badges(node)
+        };
+        assert_eq!(badges(&directory, &test_dir.join("ignored.txt")), vec![Badge::Ignored]);
+
+        //Failed move, the target already has a "f1.txt"
+        let f1_id = directory.flat_tree.find_by_path(&test_dir.join("f1.txt")).unwrap();
+        let a_id = directory.flat_tree.find_by_path(&test_dir.join("a")).unwrap();
+        directory.queue_operation(UIDOperation::MOVE(f1_id, Some(a_id)));
+        run_frame(&mut directory, &settings);
+        assert!(matches!(badges(&directory, &test_dir.join("f1.txt"))[..], [Badge::Error(_)]));
+        //Both files are still tracked
+        assert_eq!(directory.flat_tree.find_by_path(&test_dir.join("f1.txt")), Some(f1_id));
+        assert!(directory.flat_tree.find_by_path(&test_dir.join("a").join("f1.txt")).is_some());
+        assert!(test_dir.join("f1.txt").exists());
+
+        directory.queue_operation(UIDOperation::RENAME(f1_id, "f2.txt".to_string()));
+        run_frame(&mut directory, &settings);
+        assert!(badges(&directory, &test_dir.join("f2.txt")).is_empty());
+
+        //Modified on disk, once the rename is no longer recent
+        directory.own_writes.clear();
+        fs::write(test_dir.join("f2.txt"), "changed").unwrap();
+        directory.apply_deltas(vec![TreeDelta::Modify(test_dir.join("f2.txt"))]);
+        assert_eq!(badges(&directory, &test_dir.join("f2.txt")), vec![Badge::Modified]);
+
+        //Written by the editor itself
+        let f2_id = directory.flat_tree.find_by_path(&test_dir.join("f2.txt")).unwrap();
+        directory.queue_operation(UIDOperation::COPY(f2_id, Some(a_id)));
+        run_frame(&mut directory, &settings);
+        directory.apply_deltas(vec![TreeDelta::Modify(test_dir.join("a").join("f2.txt"))]);
+        assert!(badges(&directory, &test_dir.join("a").join("f2.txt")).is_empty());
+
+        //Unreadable directory, matched by a directory only pattern
+        let rules = EntryRules::new(&test_dir, &[], &["locked/".to_string()], SymlinkPolicy::default());
+        let file_type = fs::symlink_metadata(test_dir.join("a")).unwrap().file_type();
+        let error = EditorIoError::new("Permission denied", ErrorType::PermissionDenied);
+        let mut read = io::DirectoryRead::default();
+        read.add_entry(io::FileEntry::unreadable(test_dir.join("a").join("locked"), Some(file_type), &error), &rules);
+        assert_eq!(read.errors, vec![ReadError { path: test_dir.join("a").join("locked"), message: "Permission denied".to_string() }]);
+
+        directory.take_diagnostics();
+        directory.flat_tree.apply_loaded(a_id, Ok(read));
+        let locked_id = directory.flat_tree.find_by_path(&test_dir.join("a").join("locked")).unwrap();
+        assert!(directory.flat_tree.get_children_from_ids(&[locked_id])[0].file_entry.is_dir);
+        assert_eq!(badges(&directory, &test_dir.join("a").join("locked")), vec![Badge::Ignored, Badge::Error("Permission denied".to_string())]);
+        assert_eq!(directory.take_diagnostics().len(), 1);
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    /*
+        Validates that workspace folders can't be deleted or renamed, that top level
+        targets are rejected, and that entries of a folder are trashed inside of it.
+    */
+    #[test]
+    fn test_workspace_operations() {
+        let test_dir = env::temp_dir().join("reasy_test_workspace_operations");
+        let _ = fs::remove_dir_all(&test_dir);
+        let project = test_dir.join("project");
+        let library = test_dir.join("library");
+        fs::create_dir_all(&project).unwrap();
+        fs::create_dir_all(&library).unwrap();
+        fs::write(library.join("f1.txt"), "test1").unwrap();
+
+        let mut builder = TreeBuilder::init(Some(project.clone()), &EditorSettings::default()).unwrap();
+        let _ = builder.build();
+        let mut tree = builder.get_tree();
+        let mut builder = TreeBuilder::init(Some(library.clone()), &EditorSettings::default())
+            .unwrap()
+            .with_ids(tree.get_ids().clone());
+        let _ = builder.build();
+        tree.mount(builder.get_tree()).unwrap();
+
+        let mut directory = UiDirectory::new(tree, Vec::new());
+        let settings = FileTreeSettings::from(EditorSettings::default());
+        run_frame(&mut directory, &settings);
+
+        let library_id = directory.flat_tree.find_by_path(&library).unwrap();
+        let f1_id = directory.flat_tree.find_by_path(&library.join("f1.txt")).unwrap();
+
+        directory.queue_operation(UIDOperation::DELETE(library_id));
+        directory.queue_operation(UIDOperation::RENAME(library_id, "renamed".to_string()));
+        directory.queue_operation(UIDOperation::CREATE(0, "f2.txt".to_string(), false));
+        assert_eq!(directory.execute_operations().len(), 3);
+        assert!(library.exists());
+        assert!(!project.join("f2.txt").exists());
+
+        directory.queue_operation(UIDOperation::DELETE(f1_id));
+        assert!(directory.execute_operations().is_empty());
+        let trashed = &directory.trash.get_entries()[0].trashed_path;
+        assert!(trashed.starts_with(library.join(crate::utils::trash::TRASH_DIR)));
+        assert!(trashed.exists());
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    /*
+        Validates mounting and unmounting workspace folders on a built directory:
+            1. Each mounted folder, relative ones included, gets its own watcher, and changes on disk reach the right folder
+            2. Deltas of a folder are applied under it
+            3. Unmounting a folder drops its nodes and watcher, the badges of other folders are kept
+            4. Without folders the project entries are shown at the top level again
+    */
+    #[test]
+    fn test_workspace_roots() {
+        let test_dir = env::temp_dir().join("reasy_test_workspace_roots");
+        let _ = fs::remove_dir_all(&test_dir);
+        let project = test_dir.join("project");
+        let library = test_dir.join("library");
+        let extra = test_dir.join("extra");
+        fs::create_dir_all(&project).unwrap();
+        fs::create_dir_all(&library).unwrap();
+        fs::create_dir_all(&extra).unwrap();
+        fs::write(project.join("f1.txt"), "test1").unwrap();
+        fs::write(library.join("f2.txt"), "test2").unwrap();
+
+        let editor_settings = EditorSettings::default();
+        let settings = FileTreeSettings::from(editor_settings.clone());
+        let mut builder = TreeBuilder::init(Some(project.clone()), &editor_settings).unwrap();
+        builder.build_lazy();
+        let mut directory = UiDirectory::new(builder.get_tree(), watch(&project).into_iter().collect());
+        //Sibling folders given relative to the project root
+        directory.set_workspace_roots(&[library.clone(), PathBuf::from("..").join("extra")], &editor_settings);
+        run_frame(&mut directory, &settings);
+
+        let watched = |directory: &UiDirectory| directory.watchers.iter().map(|watcher| watcher.get_root().to_path_buf()).collect::<Vec<PathBuf>>();
+        assert_eq!(watched(&directory), vec![project.clone(), library.clone(), extra.clone()]);
+        assert_eq!(directory.get_workspace_roots(), vec![library.clone(), extra.clone()]);
+
+        //Created in a folder, picked up by its watcher
+        fs::write(extra.join("f3.txt"), "test3").unwrap();
+        let start = Instant::now();
+        while directory.flat_tree.find_by_path(&extra.join("f3.txt")).is_none() && start.elapsed() < Duration::from_secs(5){
+            std::thread::sleep(Duration::from_millis(50));
+            run_frame(&mut directory, &settings);
+        }
+        let extra_id = directory.flat_tree.find_by_path(&extra).unwrap();
+        let f3_id = directory.flat_tree.find_by_path(&extra.join("f3.txt")).unwrap();
+        assert_eq!(directory.flat_tree.get_parent_id(f3_id), Some(extra_id));
+
+        //Modified in another folder
+        fs::write(library.join("f2.txt"), "changed").unwrap();
+        directory.apply_deltas(vec![TreeDelta::Modify(library.join("f2.txt"))]);
+        let f2_id = directory.flat_tree.find_by_path(&library.join("f2.txt")).unwrap();
+        assert!(directory.modified.contains(&f2_id));
+
+        //Unmount
+        directory.selection.insert(f3_id);
+        directory.set_workspace_roots(std::slice::from_ref(&library), &editor_settings);
+        assert_eq!(watched(&directory), vec![project.clone(), library.clone()]);
+        assert!(!directory.flat_tree.contains(extra_id) && !directory.flat_tree.contains(f3_id));
+        assert!(directory.selection.is_empty());
+        assert!(directory.modified.contains(&f2_id));
+        assert_eq!(directory.flat_tree.find_by_path(&library.join("f2.txt")), Some(f2_id));
+
+        directory.set_workspace_roots(&[], &editor_settings);
+        run_frame(&mut directory, &settings);
+        assert_eq!(watched(&directory), vec![project.clone()]);
+        let f1_id = directory.flat_tree.find_by_path(&project.join("f1.txt")).unwrap();
+        assert_eq!(directory.display_tree, vec![f1_id]);
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    /*
+        Validates that pinned nodes follow renames and moves, are saved with the
+        tree state and are resolved and opened in a lazily read session. Resolved
+        entries are cached until the pins or the tree change.
+    */
+    #[test]
+    fn test_pinned() {
+        let test_dir = env::temp_dir().join("reasy_test_pinned");
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(test_dir.join("a").join("nested")).unwrap();
+        fs::create_dir_all(test_dir.join("b")).unwrap();
+        fs::write(test_dir.join("a").join("nested").join("file1.txt"), "test1").unwrap();
+
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &EditorSettings::default()).unwrap();
+        let _ = builder.build();
+        let mut directory = UiDirectory::new(builder.get_tree(), Vec::new());
+        let settings = FileTreeSettings::from(EditorSettings::default());
+        run_frame(&mut directory, &settings);
+
+        let a_id = directory.flat_tree.find_by_path(&test_dir.join("a")).unwrap();
+        let b_id = directory.flat_tree.find_by_path(&test_dir.join("b")).unwrap();
+        let nested_id = directory.flat_tree.find_by_path(&test_dir.join("a").join("nested")).unwrap();
+        directory.toggle_pin(nested_id);
+        directory.toggle_pin(a_id);
+        assert!(directory.is_pinned(nested_id));
+
+        //Rename and move are tracked by id
+        directory.queue_operation(UIDOperation::RENAME(a_id, "renamed".to_string()));
+        directory.queue_operation(UIDOperation::MOVE(nested_id, Some(b_id)));
+        assert!(directory.execute_operations().is_empty());
+        let pinned_paths = |directory: &UiDirectory| directory.get_pinned().into_iter().map(|(id, entry)| (id, entry.path)).collect::<Vec<(usize, PathBuf)>>();
+        assert_eq!(pinned_paths(&directory), vec![(nested_id, test_dir.join("b").join("nested")), (a_id, test_dir.join("renamed"))]);
+
+        //Deleted nodes are hidden until restored
+        directory.queue_operation(UIDOperation::DELETE(a_id));
+        assert!(directory.execute_operations().is_empty());
+        assert_eq!(directory.get_pinned().len(), 1);
+        directory.apply_history_command(HistoryCommand::Undo);
+        assert_eq!(directory.get_pinned().len(), 2);
+
+        directory.toggle_pin(a_id);
+        let state = directory.get_state();
+        assert_eq!(state.pinned, vec![PathBuf::from("b").join("nested")]);
+
+        //New session, the pinned directory is read when opened
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &EditorSettings::default()).unwrap();
+        builder.build_lazy();
+        let mut directory = UiDirectory::new(builder.get_tree(), Vec::new());
+        directory.restore_state(TreeState { pinned: state.pinned, ..TreeState::default() });
+        run_frame(&mut directory, &settings);
+
+        let pinned = directory.get_pinned();
+        assert_eq!(pinned.len(), 1);
+        let nested_id = pinned[0].0;
+        assert!(directory.flat_tree.find_by_path(&test_dir.join("b").join("nested")).is_none());
+
+        directory.open_pinned(nested_id);
+        for _ in 0..200{
+            run_frame(&mut directory, &settings);
+            if directory.restoring.is_none(){
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        run_frame(&mut directory, &settings);
+
+        assert_eq!(directory.flat_tree.find_by_path(&test_dir.join("b").join("nested")), Some(nested_id));
+        assert!(directory.is_selected(nested_id));
+        assert!(directory.display_tree.contains(&nested_id));
+
+        //Removed on disk
+        assert_eq!(directory.get_pinned().len(), 1);
+        assert!(directory.pinned_entries.borrow().is_some());
+        fs::remove_dir_all(test_dir.join("b").join("nested")).unwrap();
+        directory.apply_deltas(vec![TreeDelta::Remove(test_dir.join("b").join("nested"))]);
+        assert!(directory.pinned_entries.borrow().is_none());
+        assert!(directory.get_pinned().is_empty());
+        assert!(directory.is_pinned(nested_id));
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    /*
+        Searches a lazily read tree and validates that directories never expanded are read
+        for the filter and the file finder, without expanding them. Ignored directories are left unread,
+        and their entries are not found unless shown.
+    */
+    #[test]
+    fn test_search_lazy_tree() {
+        let test_dir = env::temp_dir().join("reasy_test_search_lazy_tree");
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(test_dir.join("a").join("b")).unwrap();
+        fs::create_dir_all(test_dir.join("target")).unwrap();
+        fs::write(test_dir.join("a").join("b").join("shader.wgsl"), "").unwrap();
+        fs::write(test_dir.join("target").join("shader.wgsl"), "").unwrap();
+        fs::write(test_dir.join(".gitignore"), "target/").unwrap();
+
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &EditorSettings::default()).unwrap();
+        builder.build_lazy();
+        let mut directory = UiDirectory::new(builder.get_tree(), Vec::new());
+        let settings = FileTreeSettings::from(EditorSettings::default());
+        run_frame(&mut directory, &settings);
+
+        let file_path = test_dir.join("a").join("b").join("shader.wgsl");
+        let run_until_read = |directory: &mut UiDirectory| {
+            for _ in 0..200{
+                run_frame(directory, &settings);
+                if directory.flat_tree.find_by_path(&file_path).is_some() && !directory.flat_tree.is_loading(){
+                    break;
+                }
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+            run_frame(directory, &settings);
+        };
+
+        //Filter
+        directory.filter.query = "shader".to_string();
+        directory.filter.update();
+        directory.mark_dirty();
+        run_until_read(&mut directory);
+
+        let file_id = directory.flat_tree.find_by_path(&file_path).unwrap();
+        assert!(directory.display_tree.contains(&file_id));
+        assert!(directory.flat_tree.find_by_path(&test_dir.join("target").join("shader.wgsl")).is_none());
+        assert!(directory.flat_tree.get_expanded_paths().is_empty());
+
+        //File finder, on a new session
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &EditorSettings::default()).unwrap();
+        builder.build_lazy();
+        let mut directory = UiDirectory::new(builder.get_tree(), Vec::new());
+        directory.finder = Some(FileFinder { query: "shader".to_string(), ..FileFinder::default() });
+        directory.mark_dirty();
+        run_until_read(&mut directory);
+
+        let file_id = directory.flat_tree.find_by_path(&file_path).unwrap();
+        assert_eq!(directory.finder.as_ref().and_then(|finder| finder.get_selected()), Some(file_id));
+
+        //Entries of ignored directories are only found if shown
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &EditorSettings::default()).unwrap();
+        builder.build().unwrap();
+        let tree = builder.get_tree();
+        let mut finder = FileFinder { query: "shader".to_string(), ..FileFinder::default() };
+        finder.search(&tree, &settings);
+        assert_eq!(finder.get_selected(), tree.find_by_path(&file_path));
+        assert_eq!(finder.results.len(), 1);
+
+        let settings = FileTreeSettings { show_ignored_elements: true, ..settings };
+        finder.search(&tree, &settings);
+        assert_eq!(finder.results.len(), 2);
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    /*
+        Reads the layers of a tree on a worker thread and validates that:
+            1. Layers of a replaced build are ignored
+            2. Layers are added under their parents, shown if the parent was expanded meanwhile
+            3. Progress is reported until the build finishes
+            4. Directories renamed meanwhile are read again under their new path, and
+               failures of their old path are ignored
+            5. The worker stops once the directory is dropped
+    */
+    #[test]
+    fn test_background_build() {
+        let test_dir = env::temp_dir().join("reasy_test_background_build");
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(test_dir.join("a").join("b").join("c")).unwrap();
+        fs::create_dir_all(test_dir.join("d")).unwrap();
+        fs::write(test_dir.join("a").join("file1.txt"), "test1").unwrap();
+        fs::write(test_dir.join("a").join("b").join("c").join("file2.txt"), "test2").unwrap();
+
+        let settings = EditorSettings { lazy_loading: false, ..EditorSettings::default() };
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &settings).unwrap();
+        let pending: Vec<PendingLayers> = builder.build_first_layer().into_iter().collect();
+        let mut directory = UiDirectory::new(builder.get_tree(), Vec::new());
+        let file_settings = FileTreeSettings::from(settings.clone());
+        run_frame(&mut directory, &file_settings);
+
+        let a_id = directory.flat_tree.find_by_path(&test_dir.join("a")).unwrap();
+        directory.flat_tree.toggle_visibility(&a_id);
+        assert!(directory.flat_tree.find_by_path(&test_dir.join("a").join("file1.txt")).is_none());
+
+        let receive = |receiver: &std::sync::mpsc::Receiver<UserEvent>| {
+            receiver.recv_timeout(std::time::Duration::from_secs(5)).unwrap()
+        };
+
+        //Replaced build
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let stale = BackgroundBuild::start(pending.clone(), move |event| sender.send(event).is_ok());
+        let (sender, current) = std::sync::mpsc::channel();
+        directory.builds = vec![(test_dir.clone(), BackgroundBuild::start(pending, move |event| sender.send(event).is_ok()))];
+        assert_ne!(stale.get_id(), directory.builds[0].1.get_id());
+        assert_eq!(directory.get_build_progress(), Some(BuildProgress { read: 0, queued: 2 }));
+
+        loop{
+            let event = receive(&receiver);
+            let finished = matches!(event, UserEvent::TreeBuilt { .. });
+            directory.apply_build_event(event);
+            if finished{
+                break;
+            }
+        }
+        assert!(directory.flat_tree.find_by_path(&test_dir.join("a").join("file1.txt")).is_none());
+
+        //Current build
+        let mut last_progress = None;
+        loop{
+            let event = receive(&current);
+            if let UserEvent::TreeLayer { progress, .. } = &event{
+                last_progress = Some(*progress);
+            }
+            let finished = matches!(event, UserEvent::TreeBuilt { .. });
+            directory.apply_build_event(event);
+            if finished{
+                break;
+            }
+        }
+        run_frame(&mut directory, &file_settings);
+
+        assert_eq!(directory.get_build_progress(), None);
+        assert_eq!(last_progress, Some(BuildProgress { read: 4, queued: 0 }));
+        let file1_id = directory.flat_tree.find_by_path(&test_dir.join("a").join("file1.txt")).unwrap();
+        let file2_id = directory.flat_tree.find_by_path(&test_dir.join("a").join("b").join("c").join("file2.txt")).unwrap();
+        assert!(directory.display_tree.contains(&file1_id));
+        assert!(!directory.display_tree.contains(&file2_id));
+
+        //Renamed while being built
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &settings).unwrap();
+        let pending: Vec<PendingLayers> = builder.build_first_layer().into_iter().collect();
+        let mut directory = UiDirectory::new(builder.get_tree(), Vec::new());
+        let (sender, _receiver) = std::sync::mpsc::channel();
+        directory.builds = vec![(test_dir.clone(), BackgroundBuild::start(Vec::new(), move |event| sender.send(event).is_ok()))];
+        let build_id = directory.builds[0].1.get_id();
+
+        let a_id = directory.flat_tree.find_by_path(&test_dir.join("a")).unwrap();
+        directory.queue_operation(UIDOperation::RENAME(a_id, "z".to_string()));
+        assert!(directory.execute_operations().is_empty());
+        let stale = crate::core::editor::objects::flat_tree::TreeLayer::read(&pending[0].directories, &pending[0].rules);
+        directory.take_diagnostics();
+        directory.apply_build_event(UserEvent::TreeLayer { build_id: build_id, layer: stale, progress: BuildProgress::default() });
+        assert!(directory.take_diagnostics().is_empty());
+        assert!(directory.flat_tree.find_by_path(&test_dir.join("z").join("file1.txt")).is_none());
+
+        directory.flat_tree.expand(a_id);
+        for _ in 0..200{
+            run_frame(&mut directory, &file_settings);
+            if !directory.flat_tree.is_loading(){
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        run_frame(&mut directory, &file_settings);
+        let file1_id = directory.flat_tree.find_by_path(&test_dir.join("z").join("file1.txt")).unwrap();
+        assert!(directory.display_tree.contains(&file1_id));
+        assert!(directory.flat_tree.find_by_path(&test_dir.join("z").join("b")).is_some());
+
+        //Dropped while building, the worker waits for each layer to be received before reading the next one
+        let mut builder = TreeBuilder::init(Some(test_dir.clone()), &settings).unwrap();
+        let pending: Vec<PendingLayers> = builder.build_first_layer().into_iter().collect();
+        let mut directory = UiDirectory::new(builder.get_tree(), Vec::new());
+        let (sender, receiver) = std::sync::mpsc::sync_channel(0);
+        directory.builds = vec![(test_dir.clone(), BackgroundBuild::start(pending, move |event| sender.send(event).is_ok()))];
+        assert!(matches!(receive(&receiver), UserEvent::TreeLayer { .. }));
+        drop(directory);
+
+        //Layer being read at the time of the drop, then neither more layers nor the end of the build
+        assert!(matches!(receive(&receiver), UserEvent::TreeLayer { .. }));
+        assert!(receiver.recv_timeout(std::time::Duration::from_secs(5)).is_err());
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+}
@@ -1,6 +1,16 @@
-
+use crate::{core::editor::objects::flat_tree::TreeLayer, utils::loader::BuildProgress};
 
 #[derive(Debug)]
 pub(crate) enum UserEvent{
-    KeyPress(String)
+    KeyPress(String),
+    ///Layer of a file tree read in the background.
+    TreeLayer{
+        build_id: usize,
+        layer: TreeLayer,
+        progress: BuildProgress
+    },
+    ///Background tree build finished.
+    TreeBuilt{
+        build_id: usize
+    }
 }
\ No newline at end of file
@@ -25,7 +25,41 @@ pub(crate) enum ErrorType{
     Unsupported,
     UnexpectedEof,
     OutOfMemory,
-    Other
+    Other,
+    //Editor Errors
+    InvalidName(NameError)
+}
+
+///Reasons an entry name is rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum NameError{
+    Empty,
+    //"." and ".."
+    Reserved,
+    InvalidCharacter(char),
+    //Windows device names, e.g. CON or LPT1
+    #[cfg_attr(not(windows), allow(dead_code))]
+    ReservedDeviceName,
+    //Windows strips trailing dots and spaces
+    #[cfg_attr(not(windows), allow(dead_code))]
+    TrailingCharacter(char),
+    TooLong,
+    //Colliding sibling name
+    Collision(String)
+}
+
+impl fmt::Display for NameError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self{
+            NameError::Empty => write!(f, "Name cannot be empty"),
+            NameError::Reserved => write!(f, "Name cannot be '.' or '..'"),
+            NameError::InvalidCharacter(character) => write!(f, "Name cannot contain {:?}", character),
+            NameError::ReservedDeviceName => write!(f, "Name is reserved by the system"),
+            NameError::TrailingCharacter(character) => write!(f, "Name cannot end with {:?}", character),
+            NameError::TooLong => write!(f, "Name is too long"),
+            NameError::Collision(sibling) => write!(f, "'{}' already exists", sibling),
+        }
+    }
 }
 
 impl From<std::io::ErrorKind> for ErrorType{
@@ -81,6 +115,14 @@ impl EditorIoError{
             error_type: etype 
         }
     }
+
+    pub(crate) fn get_type(&self) -> &ErrorType{
+        return &self.error_type
+    }
+
+    pub(crate) fn get_message(&self) -> &str{
+        return &self.message
+    }
 }
 
 impl Error for EditorIoError{
@@ -121,4 +163,23 @@ impl From<serde_json::Error> for EditorIoError{
             error_type: etype.into() 
         }
     }
+}
+
+impl From<notify::Error> for EditorIoError{
+    fn from(value: notify::Error) -> Self {
+        let msg = value.to_string();
+        let etype = match value.kind{
+            notify::ErrorKind::Io(err) => ErrorType::from(err.kind()),
+            notify::ErrorKind::PathNotFound => ErrorType::NotFound,
+            notify::ErrorKind::WatchNotFound => ErrorType::NotFound,
+            notify::ErrorKind::InvalidConfig(_) => ErrorType::InvalidInput,
+            notify::ErrorKind::MaxFilesWatch => ErrorType::ResourceBusy,
+            notify::ErrorKind::Generic(_) => ErrorType::Other,
+        };
+
+        return EditorIoError { 
+            message: msg, 
+            error_type: etype 
+        }
+    }
 }
\ No newline at end of file
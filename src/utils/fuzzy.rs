@@ -0,0 +1,115 @@
+///Bonus for a match right after the previous matched character.
+const CONSECUTIVE_BONUS: i64 = 5;
+///Bonus for a match at the start of a word (after a separator or a case change).
+const BOUNDARY_BONUS: i64 = 8;
+///Bonus for a match inside the last path component (file name).
+const FILE_NAME_BONUS: i64 = 2;
+///Bonus for a match with the exact same case.
+const CASE_BONUS: i64 = 1;
+
+
+///Result of a fuzzy match. `indices` are the char indices of the matched characters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FuzzyMatch{
+    pub(crate) score: i64,
+    pub(crate) indices: Vec<usize>
+}
+
+///Scores `candidate` against `pattern`. Every pattern character must appear in order (case-insensitive),
+/// otherwise returns None. Higher scores are better matches.
+pub(crate) fn fuzzy_match(pattern: &str, candidate: &str) -> Option<FuzzyMatch>{
+    let pattern = pattern.chars().filter(|c| !c.is_whitespace()).collect::<Vec<char>>();
+    let candidate = candidate.chars().collect::<Vec<char>>();
+
+    if pattern.is_empty(){
+        return Some(FuzzyMatch { score: 0, indices: Vec::new() })
+    }
+
+    let file_name_start = candidate
+        .iter()
+        .rposition(|c| *c == '/' || *c == '\\')
+        .map(|pos| pos + 1)
+        .unwrap_or(0);
+
+    let mut score = 0;
+    let mut indices = Vec::with_capacity(pattern.len());
+    let mut pattern_index = 0;
+
+    for (index, character) in candidate.iter().enumerate(){
+        if pattern_index == pattern.len(){
+            break;
+        }
+
+        let expected = pattern[pattern_index];
+        if !character.to_lowercase().eq(expected.to_lowercase()){
+            continue;
+        }
+
+        score += 1;
+        if *character == expected{
+            score += CASE_BONUS;
+        }
+        if index > 0 && indices.last() == Some(&(index - 1)){
+            score += CONSECUTIVE_BONUS;
+        }
+        if is_boundary(&candidate, index){
+            score += BOUNDARY_BONUS;
+        }
+        if index >= file_name_start{
+            score += FILE_NAME_BONUS;
+        }
+
+        indices.push(index);
+        pattern_index += 1;
+    }
+
+    if pattern_index < pattern.len(){
+        return None
+    }
+
+    //Penalize spread out matches and long candidates
+    let spread = indices.last().unwrap_or(&0) - indices.first().unwrap_or(&0) + 1 - indices.len();
+    score -= spread as i64;
+    score -= (candidate.len() / 16) as i64;
+
+    return Some(FuzzyMatch { score: score, indices: indices })
+}
+
+///Returns true if the character at `index` starts a word.
+fn is_boundary(candidate: &[char], index: usize) -> bool{
+    if index == 0{
+        return true
+    }
+
+    let previous = candidate[index - 1];
+    let current = candidate[index];
+
+    return matches!(previous, '/' | '\\' | '_' | '-' | '.' | ' ')
+        || (previous.is_lowercase() && current.is_uppercase())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* 
+        Validates matching, match indices and ranking of candidates.
+    */
+    #[test]
+    fn test_fuzzy_match() {
+        assert!(fuzzy_match("xyz", "src/main.rs").is_none());
+        assert_eq!(fuzzy_match("", "src/main.rs").unwrap().score, 0);
+
+        let matched = fuzzy_match("main", "src/main.rs").unwrap();
+        assert_eq!(matched.indices, vec![4, 5, 6, 7]);
+
+        //File name matches rank above scattered matches
+        let file_name = fuzzy_match("flat", "src/core/flat_tree.rs").unwrap();
+        let scattered = fuzzy_match("flat", "src/foo/lib/a_test.rs").unwrap();
+        assert!(file_name.score > scattered.score);
+
+        //Case-insensitive
+        assert!(fuzzy_match("CARGO", "Cargo.toml").is_some());
+    }
+}
@@ -1,37 +1,325 @@
 use core::fmt;
-use std::{fs::{self, DirEntry, Metadata}, path::{Path, PathBuf}};
+use std::{fs::{self, DirEntry, FileType, Metadata}, path::{Component, Path, PathBuf}};
 
 use serde::{de::DeserializeOwned, Serialize};
 
-use crate::utils::error::{EditorIoError, ErrorType};
+use crate::utils::{error::{EditorIoError, ErrorType, NameError}, rules::EntryRules};
+
+
+///Characters that can't be part of an entry name.
+#[cfg(windows)]
+const INVALID_CHARACTERS: [char; 9] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+#[cfg(not(windows))]
+const INVALID_CHARACTERS: [char; 1] = ['/'];
+
+///Device names reserved by Windows, with or without an extension.
+#[cfg(windows)]
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9"
+];
+
+///Maximum length of an entry name in bytes, on most filesystems.
+const MAX_NAME_LENGTH: usize = 255;
+
+///Contents of a directory read. Failing entries don't abort the read, they are kept
+/// as unreadable entries (see `FileEntry::error`) and listed in `errors`.
+#[derive(Debug, Default)]
+pub(crate) struct DirectoryRead{
+    pub(crate) entries: Vec<FileEntry>,
+    pub(crate) errors: Vec<ReadError>
+}
+impl DirectoryRead{
+    ///Applies `rules` to an entry and adds it, listing its error if it is unreadable.
+    pub(crate) fn add_entry(&mut self, mut entry: FileEntry, rules: &EntryRules){
+        rules.apply(&mut entry);
+
+        if let Some(message) = &entry.error{
+            self.errors.push(ReadError {
+                path: entry.path.clone(),
+                message: message.clone()
+            });
+        }
+        self.entries.push(entry);
+    }
+}
+
+///Failure reading an entry or a directory.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ReadError{
+    pub(crate) path: PathBuf,
+    pub(crate) message: String
+}
+impl ReadError{
+    pub(crate) fn new(path: &Path, error: &EditorIoError) -> ReadError{
+        return ReadError {
+            path: path.to_path_buf(),
+            message: error.get_message().to_string()
+        }
+    }
+}
+
+impl fmt::Display for ReadError{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Failed reading {}: {}", self.path.display(), self.message)
+    }
+}
 
 ///Reads and returns a single directory. Does not recurse.
-pub(crate) fn read_directory(path: &Path) -> Result<Vec<FileEntry>, EditorIoError>{
+/// Entries are marked as hidden or ignored based on `rules`.
+/// Fails only if the directory itself can't be read.
+pub(crate) fn read_directory(path: &Path, rules: &EntryRules) -> Result<DirectoryRead, EditorIoError>{
     if !path.is_dir(){
         return Err(EditorIoError::new("Path not a directory", ErrorType::NotADirectory))
     }
 
-    let mut directory_tree = vec![];
+    let mut directory_read = DirectoryRead::default();
+
+    for entry in fs::read_dir(path)?{
+        let entry_converted: FileEntry = match entry{
+            Ok(entry) => entry.into(),
+            //Failed reading directory element, its name is unknown
+            Err(err) => {
+                directory_read.errors.push(ReadError::new(path, &err.into()));
+                continue;
+            },
+        };
+        directory_read.add_entry(entry_converted, rules);
+    }
+
+    return Ok(directory_read)
+}
+
+///Checks a single entry name (not a path) against the platform rules.
+pub(crate) fn validate_name(name: &str) -> Result<(), NameError>{
+    if name.trim().is_empty(){
+        return Err(NameError::Empty)
+    }
+    if name == "." || name == ".."{
+        return Err(NameError::Reserved)
+    }
+    if name.len() > MAX_NAME_LENGTH{
+        return Err(NameError::TooLong)
+    }
+    if let Some(character) = name.chars().find(|character| INVALID_CHARACTERS.contains(character) || is_invalid_control(*character)){
+        return Err(NameError::InvalidCharacter(character))
+    }
+
+    return validate_platform_name(name)
+}
+
+#[cfg(windows)]
+fn is_invalid_control(character: char) -> bool{
+    return (character as u32) < 32
+}
+
+#[cfg(not(windows))]
+fn is_invalid_control(character: char) -> bool{
+    return character == '\0'
+}
+
+#[cfg(windows)]
+fn validate_platform_name(name: &str) -> Result<(), NameError>{
+    let stem = name.split('.').next().unwrap_or(name).trim_end();
+    if RESERVED_NAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(stem)){
+        return Err(NameError::ReservedDeviceName)
+    }
+    if let Some(character) = name.chars().last().filter(|character| *character == '.' || *character == ' '){
+        return Err(NameError::TrailingCharacter(character))
+    }
+
+    return Ok(())
+}
+
+#[cfg(not(windows))]
+fn validate_platform_name(_name: &str) -> Result<(), NameError>{
+    return Ok(())
+}
+
+///Returns true if two entry names refer to the same entry, case insensitive on Windows and macOS.
+pub(crate) fn names_collide(first: &str, second: &str) -> bool{
+    if cfg!(any(windows, target_os = "macos")){
+        return first.to_lowercase() == second.to_lowercase()
+    }
+
+    return first == second
+}
+
+///Reads and returns a single entry, file or directory.
+pub(crate) fn read_entry(path: &Path, rules: &EntryRules) -> Result<FileEntry, EditorIoError>{
+    let metadata = fs::symlink_metadata(path)?;
+
+    let mut entry = FileEntry::new(path.to_path_buf(), metadata);
+    rules.apply(&mut entry);
+
+    return Ok(entry)
+}
+
+///Renames (or moves) a file or directory. Fails if the target already exists, broken symlinks included,
+/// unless it is the same entry renamed to a different case on a case insensitive filesystem.
+pub(crate) fn rename_entry(from: &Path, to: &Path) -> Result<(), EditorIoError>{
+    if to.symlink_metadata().is_ok() && !is_case_rename(from, to){
+        return Err(EditorIoError::new(&format!("Target already exists: {}", to.display()), ErrorType::AlreadyExists))
+    }
+
+    let renamed = fs::rename(from, to)
+        .map_err(|err| EditorIoError::from(err));
+
+    return renamed
+}
+
+///Returns true if two sibling paths have names differing only in case.
+fn is_case_variant(from: &Path, to: &Path) -> bool{
+    let (from_name, to_name) = (get_name(from), get_name(to));
+
+    return from.parent() == to.parent() && from_name != to_name && from_name.to_lowercase() == to_name.to_lowercase()
+}
+
+///Returns true if `to` names the entry at `from` in a different case, which only exists on case insensitive filesystems.
+#[cfg(unix)]
+fn is_case_rename(from: &Path, to: &Path) -> bool{
+    use std::os::unix::fs::MetadataExt;
+
+    if !is_case_variant(from, to){
+        return false
+    }
+
+    return match (fs::symlink_metadata(from), fs::symlink_metadata(to)){
+        (Ok(from), Ok(to)) => from.dev() == to.dev() && from.ino() == to.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(windows)]
+fn is_case_rename(from: &Path, to: &Path) -> bool{
+    return is_case_variant(from, to)
+}
+
+///Creates an empty file or directory. Fails if the target already exists.
+pub(crate) fn create_entry(path: &Path, is_dir: bool) -> Result<(), EditorIoError>{
+    let created = if is_dir{
+        fs::create_dir(path)
+    }
+    else{
+        fs::File::create_new(path).map(|_| ())
+    };
+
+    return created.map_err(|err| EditorIoError::from(err))
+}
 
-    match fs::read_dir(path){
-        Ok(directory) => {
-            for entry in directory{
-                //Failed reading directory element
-                if entry.is_err(){
-                    return Err(entry.unwrap_err().into())
-                }
-                let entry_unwraped = entry.unwrap();
+///Removes a file, or a directory including all of its contents.
+pub(crate) fn remove_entry(path: &Path) -> Result<(), EditorIoError>{
+    let metadata = fs::symlink_metadata(path)?;
 
-                let entry_converted: FileEntry = entry_unwraped.into();
-                directory_tree.push(entry_converted.clone());
-            }
-        },
-        Err(err) => {
-            return Err(err.into())
-        },
+    let removed = if metadata.is_dir(){
+        fs::remove_dir_all(path)
     }
+    else{
+        fs::remove_file(path)
+    };
 
-    return Ok(directory_tree)
+    return removed.map_err(|err| EditorIoError::from(err))
+}
+
+///Copies a file, or a directory including all of its contents. Symlinks are copied as links.
+/// Fails if the target already exists or is inside the copied directory.
+pub(crate) fn copy_entry(from: &Path, to: &Path) -> Result<(), EditorIoError>{
+    if to.symlink_metadata().is_ok(){
+        return Err(EditorIoError::new(&format!("Target already exists: {}", to.display()), ErrorType::AlreadyExists))
+    }
+    if to.starts_with(from){
+        return Err(EditorIoError::new("Cannot copy a directory into itself", ErrorType::InvalidInput))
+    }
+
+    let metadata = fs::symlink_metadata(from)?;
+
+    if metadata.is_symlink(){
+        copy_symlink(from, to)?;
+    }
+    else if metadata.is_dir(){
+        fs::create_dir(to)?;
+
+        for entry in fs::read_dir(from)?{
+            let entry = entry?;
+            copy_entry(&entry.path(), &to.join(entry.file_name()))?;
+        }
+    }
+    else{
+        fs::copy(from, to)?;
+    }
+
+    return Ok(())
+}
+
+#[cfg(unix)]
+fn copy_symlink(from: &Path, to: &Path) -> Result<(), EditorIoError>{
+    let target = fs::read_link(from)?;
+    std::os::unix::fs::symlink(target, to)?;
+
+    return Ok(())
+}
+
+#[cfg(windows)]
+fn copy_symlink(from: &Path, to: &Path) -> Result<(), EditorIoError>{
+    let target = fs::read_link(from)?;
+
+    if fs::metadata(from).is_ok_and(|metadata| metadata.is_dir()){
+        std::os::windows::fs::symlink_dir(target, to)?;
+    }
+    else{
+        std::os::windows::fs::symlink_file(target, to)?;
+    }
+
+    return Ok(())
+}
+
+///Returns `path` if nothing exists there, otherwise the first free "name (n).ext" sibling.
+pub(crate) fn unique_path(path: &Path) -> PathBuf{
+    if path.symlink_metadata().is_err(){
+        return path.to_path_buf()
+    }
+
+    //Directories keep their whole name
+    let (stem, extension) = match (path.file_stem(), path.extension()){
+        (Some(stem), Some(extension)) if !path.is_dir() => (stem.to_string_lossy(), Some(extension.to_string_lossy())),
+        _ => (path.file_name().unwrap_or_default().to_string_lossy(), None),
+    };
+
+    let mut index = 1;
+    loop{
+        let name = match &extension{
+            Some(extension) => format!("{} ({}).{}", stem, index, extension),
+            None => format!("{} ({})", stem, index),
+        };
+        let candidate = path.with_file_name(name);
+
+        if candidate.symlink_metadata().is_err(){
+            return candidate
+        }
+        index += 1;
+    }
+}
+
+///Resolves `path` relative to `base` into the form it is compared and stored in. Symlinks are resolved
+/// if the path exists, otherwise `.` and `..` components are removed without touching the disk.
+pub(crate) fn resolve_path(base: &Path, path: &Path) -> PathBuf{
+    let joined = base.join(path);
+    if let Ok(canonical) = fs::canonicalize(&joined){
+        return canonical
+    }
+
+    let mut normalized = PathBuf::new();
+    for component in joined.components(){
+        match component{
+            Component::CurDir => {},
+            Component::ParentDir => {
+                normalized.pop();
+            },
+            _ => normalized.push(component),
+        }
+    }
+    return normalized
 }
 
 ///Reads a file and deserialized into a concrete struct.
@@ -58,6 +346,19 @@ pub(crate) fn write_serialized_data<T: Serialize, P: AsRef<Path>>(settings: &T,
 
 
 
+///How a symbolic link entry was resolved, see `SymlinkPolicy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LinkStatus{
+    ///Read as its target.
+    Followed,
+    ///Directory not read due to the symlink policy.
+    NotFollowed,
+    ///Directory not read, as it contains the link.
+    Cycle,
+    ///Target doesn't exist.
+    Broken
+}
+
 ///Structure type that imitates a DirEntry, in order to be bale to perform more operations when reading a directory.
 #[allow(dead_code)]
 #[derive(Clone)]
@@ -68,41 +369,86 @@ pub(crate) struct FileEntry {
     pub(crate) is_dir: bool,
     pub(crate) is_file: bool,
     pub(crate) is_symlink: bool,
+    pub(crate) is_hidden: bool,
+    pub(crate) is_ignored: bool,
     pub(crate) size: Option<u64>,
     pub(crate) modified: Option<std::time::SystemTime>,
-    pub(crate) metadata: Metadata
+    ///Resolution of a symbolic link, set when rules are applied.
+    pub(crate) link: Option<LinkStatus>,
+    ///Reason the entry or its contents couldn't be read.
+    pub(crate) error: Option<String>,
+    ///None for unreadable entries.
+    pub(crate) metadata: Option<Metadata>
 }
 
-impl Into<FileEntry> for DirEntry {
-    fn into(self) -> FileEntry {
-        let metadata = self.metadata().unwrap();
+impl FileEntry{
+    ///Creates an entry from a path and its (non traversed) metadata.
+    pub(crate) fn new(full_path: PathBuf, metadata: Metadata) -> FileEntry{
         let file_type = metadata.file_type();
         let size = metadata.len();
-        let full_path = self.path();
-
-        let parent_path = {
-            let mut components = full_path.components();
-            let _ = components.next_back();
-
-            if let Some(last) = components.next_back(){
-                last.as_os_str().to_string_lossy().to_string()
-            }
-            else{
-                ".".to_string()
-            }
-        };
         let modified = metadata.modified().ok();
 
         FileEntry {
-            parent: parent_path,
-            name: self.file_name().to_string_lossy().to_string(),
+            parent: get_parent_name(&full_path),
+            name: get_name(&full_path),
             path: full_path,
             is_dir: file_type.is_dir(),
             is_file: file_type.is_file(),
             is_symlink: file_type.is_symlink(),
+            is_hidden: false,
+            is_ignored: false,
             size: Some(size),
             modified,
-            metadata
+            link: None,
+            error: None,
+            metadata: Some(metadata)
+        }
+    }
+
+    ///Creates an entry whose metadata couldn't be read, e.g. removed while reading its directory.
+    /// The file type, when known from the directory listing, keeps directories recognized as such.
+    pub(crate) fn unreadable(full_path: PathBuf, file_type: Option<FileType>, error: &EditorIoError) -> FileEntry{
+        FileEntry {
+            parent: get_parent_name(&full_path),
+            name: get_name(&full_path),
+            path: full_path,
+            is_dir: file_type.is_some_and(|file_type| file_type.is_dir()),
+            is_file: file_type.is_some_and(|file_type| file_type.is_file()),
+            is_symlink: file_type.is_some_and(|file_type| file_type.is_symlink()),
+            is_hidden: false,
+            is_ignored: false,
+            size: None,
+            modified: None,
+            link: None,
+            error: Some(error.get_message().to_string()),
+            metadata: None
+        }
+    }
+}
+
+///Name of the directory containing `path`, `.` if none.
+fn get_parent_name(path: &Path) -> String{
+    let mut components = path.components();
+    let _ = components.next_back();
+
+    if let Some(last) = components.next_back(){
+        return last.as_os_str().to_string_lossy().to_string()
+    }
+    return ".".to_string()
+}
+
+fn get_name(path: &Path) -> String{
+    return path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+impl Into<FileEntry> for DirEntry {
+    fn into(self) -> FileEntry {
+        match self.metadata(){
+            Ok(metadata) => return FileEntry::new(self.path(), metadata),
+            Err(err) => return FileEntry::unreadable(self.path(), self.file_type().ok(), &err.into()),
         }
     }
 }
@@ -121,4 +467,70 @@ impl fmt::Debug for FileEntry{
         // .field("metadata", &self.metadata)
         .finish()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    /* 
+        Renames entries to names differing only in case and validates that
+            1. A different entry under the colliding name is not overwritten
+            2. The same entry under the colliding name, as on case insensitive filesystems, is renamed
+            3. A broken symlink under the target name is not overwritten
+    */
+    #[test]
+    #[cfg(unix)]
+    fn test_case_rename() {
+        let test_dir = env::temp_dir().join("reasy_test_case_rename");
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(&test_dir).unwrap();
+        fs::write(test_dir.join("file.txt"), "test").unwrap();
+        fs::write(test_dir.join("File.txt"), "other").unwrap();
+
+        assert!(!is_case_rename(&test_dir.join("file.txt"), &test_dir.join("File.txt")));
+        assert!(rename_entry(&test_dir.join("file.txt"), &test_dir.join("File.txt")).is_err());
+        assert_eq!(fs::read_to_string(test_dir.join("File.txt")).unwrap(), "other");
+
+        //A hard link resolves to the same entry, like a case insensitive lookup
+        fs::remove_file(test_dir.join("File.txt")).unwrap();
+        fs::hard_link(test_dir.join("file.txt"), test_dir.join("File.txt")).unwrap();
+        assert!(is_case_rename(&test_dir.join("file.txt"), &test_dir.join("File.txt")));
+        assert!(!is_case_rename(&test_dir.join("file.txt"), &test_dir.join("file.txt")));
+        assert!(rename_entry(&test_dir.join("file.txt"), &test_dir.join("File.txt")).is_ok());
+
+        //A broken symlink is an existing target
+        std::os::unix::fs::symlink(test_dir.join("missing"), test_dir.join("link")).unwrap();
+        assert!(rename_entry(&test_dir.join("File.txt"), &test_dir.join("link")).is_err());
+        assert!(test_dir.join("link").symlink_metadata().is_ok_and(|metadata| metadata.file_type().is_symlink()));
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    /* 
+        Resolves paths against a base and validates that
+            1. Existing paths are canonicalized, symlinks included
+            2. Missing paths lose their `.` and `..` components
+    */
+    #[test]
+    #[cfg(unix)]
+    fn test_resolve_path() {
+        let test_dir = env::temp_dir().join("reasy_test_resolve_path");
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(test_dir.join("project")).unwrap();
+        fs::create_dir_all(test_dir.join("library")).unwrap();
+        std::os::unix::fs::symlink(test_dir.join("library"), test_dir.join("link")).unwrap();
+        let test_dir = fs::canonicalize(test_dir).unwrap();
+        let project = test_dir.join("project");
+
+        assert_eq!(resolve_path(&project, Path::new("../library")), test_dir.join("library"));
+        assert_eq!(resolve_path(&project, Path::new("./../link")), test_dir.join("library"));
+        assert_eq!(resolve_path(&project, &test_dir.join("library")), test_dir.join("library"));
+        assert_eq!(resolve_path(&project, Path::new("../missing/./nested/..")), test_dir.join("missing"));
+
+        // Cleanup
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+}
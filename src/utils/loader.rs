@@ -0,0 +1,147 @@
+use core::fmt;
+use std::{path::PathBuf, rc::Rc, sync::{atomic::{AtomicBool, AtomicUsize, Ordering}, mpsc::{self, Receiver, Sender}, Arc}, thread};
+
+use crate::{core::editor::objects::flat_tree::{PendingLayers, TreeLayer}, event::UserEvent, utils::{error::EditorIoError, io::{self, DirectoryRead}, rules::EntryRules}};
+
+
+///Id of the next background build, so that layers of a replaced build can be told apart.
+static NEXT_BUILD_ID: AtomicUsize = AtomicUsize::new(1);
+
+
+///Result of a background directory read, keyed by the requesting node id.
+pub(crate) type LoadResult = (usize, Result<DirectoryRead, EditorIoError>);
+
+///Reads directories on worker threads, so the UI never blocks on IO.
+pub(crate) struct DirectoryLoader{
+    sender: Sender<LoadResult>,
+    receiver: Receiver<LoadResult>
+}
+impl DirectoryLoader{
+    pub(crate) fn new() -> DirectoryLoader{
+        let (sender, receiver) = mpsc::channel();
+
+        return DirectoryLoader { 
+            sender: sender, 
+            receiver: receiver 
+        }
+    }
+
+    ///Starts reading directories in the background, one after the other on a single worker thread.
+    pub(crate) fn request(&self, requests: Vec<(usize, PathBuf, EntryRules)>){
+        if requests.is_empty(){
+            return;
+        }
+        let sender = self.sender.clone();
+
+        thread::spawn(move || {
+            for (id, path, rules) in requests{
+                let directory = io::read_directory(&path, &rules);
+                if sender.send((id, directory)).is_err(){
+                    return;
+                }
+            }
+        });
+    }
+
+    ///Drains all finished reads without blocking.
+    pub(crate) fn poll(&self) -> Vec<LoadResult>{
+        return self.receiver.try_iter().collect()
+    }
+}
+
+impl fmt::Debug for DirectoryLoader{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DirectoryLoader")
+        .finish()
+    }
+}
+
+
+
+///Directories read so far by a background build, and directories known to be left.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct BuildProgress{
+    pub(crate) read: usize,
+    pub(crate) queued: usize
+}
+
+///Stops the worker of a build once the last handle of the build is dropped.
+#[derive(Debug)]
+struct CancelGuard{
+    cancelled: Arc<AtomicBool>
+}
+impl Drop for CancelGuard{
+    fn drop(&mut self){
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+///Reads the remaining layers of a tree on a worker thread, sending each one as a `UserEvent::TreeLayer`,
+/// followed by `UserEvent::TreeBuilt`. Layers of a folder are sent in order, parents first.
+/// Clones share the worker, which is cancelled once every clone is dropped.
+#[derive(Debug, Clone)]
+pub(crate) struct BackgroundBuild{
+    id: usize,
+    guard: Rc<CancelGuard>,
+    progress: BuildProgress
+}
+impl BackgroundBuild{
+    ///Starts reading `pending`. The worker stops once cancelled or when `send` fails.
+    pub(crate) fn start(pending: Vec<PendingLayers>, send: impl Fn(UserEvent) -> bool + Send + 'static) -> BackgroundBuild{
+        let id = NEXT_BUILD_ID.fetch_add(1, Ordering::Relaxed);
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let progress = BuildProgress {
+            read: 0,
+            queued: pending.iter().map(|root| root.directories.len()).sum()
+        };
+
+        let worker_cancelled = cancelled.clone();
+        thread::spawn(move || {
+            let mut progress = progress;
+
+            for root in pending{
+                let mut directories = root.directories;
+
+                while !directories.is_empty(){
+                    if worker_cancelled.load(Ordering::Relaxed){
+                        return;
+                    }
+
+                    let layer = TreeLayer::read(&directories, &root.rules);
+                    let read = std::mem::replace(&mut directories, layer.get_directories()).len();
+                    progress.read += read;
+                    progress.queued = progress.queued - read + directories.len();
+
+                    if !send(UserEvent::TreeLayer { build_id: id, layer: layer, progress: progress }){
+                        return;
+                    }
+                }
+            }
+
+            let _ = send(UserEvent::TreeBuilt { build_id: id });
+        });
+
+        return BackgroundBuild {
+            id: id,
+            guard: Rc::new(CancelGuard { cancelled: cancelled }),
+            progress: progress
+        }
+    }
+
+    pub(crate) fn get_id(&self) -> usize{
+        return self.id
+    }
+
+    pub(crate) fn get_progress(&self) -> BuildProgress{
+        return self.progress
+    }
+
+    pub(crate) fn set_progress(&mut self, progress: BuildProgress){
+        self.progress = progress;
+    }
+
+    ///Stops the worker before its next layer. Layers already sent are ignored by their build id.
+    pub(crate) fn cancel(&self){
+        self.guard.cancelled.store(true, Ordering::Relaxed);
+    }
+}
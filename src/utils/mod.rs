@@ -1,2 +1,7 @@
 pub(crate) mod io;
-pub(crate) mod error;
\ No newline at end of file
+pub(crate) mod error;
+pub(crate) mod watcher;
+pub(crate) mod loader;
+pub(crate) mod rules;
+pub(crate) mod fuzzy;
+pub(crate) mod trash;
\ No newline at end of file
@@ -0,0 +1,271 @@
+use std::{collections::HashMap, fs::{self, Metadata}, path::{Path, PathBuf}, sync::{Arc, RwLock}};
+
+use glob::Pattern;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{io::{FileEntry, LinkStatus}, trash::TRASH_DIR};
+
+///Ignore files honored in every directory, ordered by precedence.
+const IGNORE_FILES: [&str; 2] = [".ignore", ".gitignore"];
+
+
+///Whether symlinked directories are read as part of the tree. Links to files are always resolved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub(crate) enum SymlinkPolicy{
+    ///Links are shown as entries without children.
+    DontFollow,
+    ///Links are followed, except the ones found inside a followed link.
+    FollowOnce,
+    ///Links are followed, unless they point to a directory they are inside of.
+    /// Compared by canonical path, so loops through several links are detected too.
+    #[default]
+    Follow
+}
+
+impl SymlinkPolicy{
+    pub(crate) const ALL: [SymlinkPolicy; 3] = [
+        SymlinkPolicy::DontFollow,
+        SymlinkPolicy::FollowOnce,
+        SymlinkPolicy::Follow
+    ];
+}
+
+impl std::fmt::Display for SymlinkPolicy{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self{
+            SymlinkPolicy::DontFollow => write!(f, "Don't follow"),
+            SymlinkPolicy::FollowOnce => write!(f, "Follow once"),
+            SymlinkPolicy::Follow => write!(f, "Follow (skip loops)"),
+        }
+    }
+}
+
+
+///Rules applied to every entry read from disk, marking it as hidden or ignored.
+/// 
+/// Hidden entries are hidden by the platform or match `hidden_patterns`.
+/// Ignored entries match the editor-level `excludes` or any `.ignore`/`.gitignore` 
+/// file between `root` and the entry. Deeper ignore files take precedence.
+/// Symbolic links are resolved based on `symlink_policy`.
+#[derive(Debug, Clone)]
+pub(crate) struct EntryRules{
+    //No ignore rules are applied without a root
+    root: Option<PathBuf>,
+    hidden_patterns: Vec<Pattern>,
+    excludes: Gitignore,
+    symlink_policy: SymlinkPolicy,
+    //Parsed ignore files per directory, shared between clones (loader threads).
+    ignore_files: Arc<RwLock<HashMap<PathBuf, Arc<Vec<Gitignore>>>>>
+}
+impl EntryRules{
+    pub(crate) fn new(root: &Path, hidden_patterns: &[String], exclude_patterns: &[String], symlink_policy: SymlinkPolicy) -> EntryRules{
+        let mut builder = GitignoreBuilder::new(root);
+        for pattern in exclude_patterns{
+            if let Err(err) = builder.add_line(None, pattern){
+                eprintln!("Invalid exclude pattern {}: {}", pattern, err);
+            }
+        }
+        //Trash is managed by the editor
+        let _ = builder.add_line(None, &format!("/{}/", TRASH_DIR));
+        let excludes = builder.build().unwrap_or_else(|err| {
+            eprintln!("Failed building exclude patterns: {}", err);
+            Gitignore::empty()
+        });
+
+        return EntryRules { 
+            root: Some(root.to_path_buf()), 
+            hidden_patterns: compile_patterns(hidden_patterns), 
+            excludes: excludes, 
+            symlink_policy: symlink_policy,
+            ignore_files: Arc::new(RwLock::new(HashMap::new())) 
+        }
+    }
+
+    ///Marks an entry as hidden and/or ignored. Followed symlinks take the type of their target.
+    pub(crate) fn apply(&self, entry: &mut FileEntry){
+        if entry.is_symlink{
+            let status = self.resolve_link(&entry.path);
+            if status == LinkStatus::Followed{
+                if let Ok(target) = fs::metadata(&entry.path){
+                    entry.is_dir = target.is_dir();
+                    entry.is_file = target.is_file();
+                }
+            }
+            entry.link = Some(status);
+        }
+
+        entry.is_hidden = is_hidden_entry(&entry.name, entry.metadata.as_ref())
+            || self.hidden_patterns.iter().any(|pattern| pattern.matches(&entry.name));
+        entry.is_ignored = self.is_ignored(&entry.path, entry.is_dir);
+    }
+
+    ///Checks a path against the editor excludes and the ignore files of its ancestors.
+    pub(crate) fn is_ignored(&self, path: &Path, is_dir: bool) -> bool{
+        let root = match &self.root{
+            Some(root) => root,
+            None => return false,
+        };
+        let relative = match path.strip_prefix(root){
+            Ok(relative) => relative,
+            Err(_) => return false,
+        };
+
+        if self.excludes.matched_path_or_any_parents(relative, is_dir).is_ignore(){
+            return true
+        }
+
+        //Walk from the entry's directory up to root
+        for directory in path.ancestors().skip(1){
+            if !directory.starts_with(root){
+                break;
+            }
+
+            for ignore_file in self.get_ignore_files(directory).iter(){
+                let matched = ignore_file.matched_path_or_any_parents(path, is_dir);
+
+                if matched.is_ignore(){
+                    return true
+                }
+                if matched.is_whitelist(){
+                    return false
+                }
+            }
+        }
+
+        return false
+    }
+
+    ///Resolves a symbolic link based on the symlink policy.
+    fn resolve_link(&self, path: &Path) -> LinkStatus{
+        let target = match fs::canonicalize(path){
+            Ok(target) => target,
+            Err(_) => return LinkStatus::Broken,
+        };
+        if !target.is_dir(){
+            return LinkStatus::Followed
+        }
+
+        match self.symlink_policy{
+            SymlinkPolicy::DontFollow => return LinkStatus::NotFollowed,
+            SymlinkPolicy::FollowOnce => {
+                let inside_link = self.get_ancestors(path)
+                    .any(|ancestor| fs::symlink_metadata(ancestor).is_ok_and(|metadata| metadata.is_symlink()));
+
+                if inside_link{
+                    return LinkStatus::NotFollowed
+                }
+                return LinkStatus::Followed
+            },
+            SymlinkPolicy::Follow => {
+                //Following a link to a directory containing it would never end
+                let is_cycle = self.get_ancestors(path)
+                    .filter_map(|ancestor| fs::canonicalize(ancestor).ok())
+                    .any(|ancestor| ancestor.starts_with(&target));
+
+                if is_cycle{
+                    return LinkStatus::Cycle
+                }
+                return LinkStatus::Followed
+            },
+        }
+    }
+
+    pub(crate) fn get_symlink_policy(&self) -> SymlinkPolicy{
+        return self.symlink_policy
+    }
+
+    ///Directories containing `path`, up to and including root.
+    fn get_ancestors<'a>(&'a self, path: &'a Path) -> impl Iterator<Item = &'a Path>{
+        return path.ancestors()
+            .skip(1)
+            .take_while(|ancestor| self.root.as_ref().is_none_or(|root| ancestor.starts_with(root)))
+    }
+
+    ///Drops the parsed ignore files of the directory containing `path`, if `path` is an ignore file.
+    /// Returns true if dropped, entries of that directory have to be checked again.
+    pub(crate) fn invalidate(&self, path: &Path) -> bool{
+        let is_ignore_file = path.file_name().is_some_and(|name| IGNORE_FILES.iter().any(|ignore_file| name == *ignore_file));
+        let directory = match path.parent(){
+            Some(directory) if is_ignore_file => directory,
+            _ => return false,
+        };
+
+        if let Ok(mut cache) = self.ignore_files.write(){
+            cache.remove(directory);
+        }
+        return true
+    }
+
+    ///Retrieves (and caches) the parsed ignore files of a directory.
+    fn get_ignore_files(&self, directory: &Path) -> Arc<Vec<Gitignore>>{
+        if let Ok(cache) = self.ignore_files.read(){
+            if let Some(files) = cache.get(directory){
+                return files.clone()
+            }
+        }
+
+        let files = IGNORE_FILES
+            .iter()
+            .map(|name| directory.join(name))
+            .filter(|path| path.is_file())
+            .filter_map(|path| {
+                let (ignore_file, err) = Gitignore::new(&path);
+                if let Some(err) = err{
+                    eprintln!("Error parsing {}: {}", path.display(), err);
+                }
+                (!ignore_file.is_empty()).then_some(ignore_file)
+            })
+            .collect::<Vec<Gitignore>>();
+        let files = Arc::new(files);
+
+        if let Ok(mut cache) = self.ignore_files.write(){
+            cache.insert(directory.to_path_buf(), files.clone());
+        }
+
+        return files
+    }
+}
+
+impl Default for EntryRules{
+    fn default() -> EntryRules {
+        return EntryRules { 
+            root: None, 
+            hidden_patterns: Vec::new(), 
+            excludes: Gitignore::empty(), 
+            symlink_policy: SymlinkPolicy::default(),
+            ignore_files: Arc::new(RwLock::new(HashMap::new())) 
+        }
+    }
+}
+
+///Compiles glob patterns used to hide entries. Invalid patterns are skipped.
+fn compile_patterns(patterns: &[String]) -> Vec<Pattern>{
+    return patterns
+        .iter()
+        .filter_map(|pattern| {
+            match Pattern::new(pattern){
+                Ok(compiled) => Some(compiled),
+                Err(err) => {
+                    eprintln!("Invalid pattern {}: {}", pattern, err);
+                    None
+                },
+            }
+        })
+        .collect()
+}
+
+///Platform check for hidden entries. On Windows checks the hidden attribute.
+#[cfg(windows)]
+fn is_hidden_entry(_name: &str, metadata: Option<&Metadata>) -> bool{
+    use std::os::windows::fs::MetadataExt;
+
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    return metadata.is_some_and(|metadata| metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0)
+}
+
+///Platform check for hidden entries. On Unix checks for dot-prefixed names.
+#[cfg(not(windows))]
+fn is_hidden_entry(name: &str, _metadata: Option<&Metadata>) -> bool{
+    return name.starts_with('.')
+}
\ No newline at end of file
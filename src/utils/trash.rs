@@ -0,0 +1,167 @@
+use std::{path::{Path, PathBuf}, time::{SystemTime, UNIX_EPOCH}};
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{error::{EditorIoError, ErrorType}, io};
+
+
+///Project-local trash directory, relative to the project root. Excluded from the file tree.
+pub(crate) const TRASH_DIR: &str = ".trash";
+///Manifest of the trashed entries, inside the trash directory.
+const MANIFEST_FILE: &str = "manifest.json";
+
+///Entry moved into the trash.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct TrashEntry{
+    pub(crate) original_path: PathBuf,
+    pub(crate) trashed_path: PathBuf,
+    ///Seconds since UNIX epoch.
+    pub(crate) deleted_at: u64
+}
+
+///Project-local trash. Deleted entries are moved into `directory` and recorded in its manifest,
+/// so that they can be restored to their original paths.
+/// Entries of other workspace folders are moved into the trash directory of their folder, see `add_root`.
+#[derive(Debug, Clone)]
+pub(crate) struct Trash{
+    directory: PathBuf,
+    //Workspace folders other than the project root
+    roots: Vec<PathBuf>,
+    entries: Vec<TrashEntry>
+}
+impl Trash{
+    ///Opens the trash of a project root, reading its manifest if exists.
+    pub(crate) fn open(root: &Path) -> Trash{
+        let directory = root.join(TRASH_DIR);
+        let manifest = directory.join(MANIFEST_FILE);
+
+        let entries = if manifest.exists(){
+            io::read_serialized_data(&manifest).unwrap_or_else(|err| {
+                eprintln!("Failed reading trash manifest: {}", err);
+                Vec::new()
+            })
+        }
+        else{
+            Vec::new()
+        };
+
+        return Trash {
+            directory: directory,
+            roots: Vec::new(),
+            entries: entries
+        }
+    }
+
+    ///Adds a workspace folder, whose entries are trashed inside of it, so they are never moved across filesystems.
+    pub(crate) fn add_root(&mut self, root: &Path){
+        if !self.roots.iter().any(|existing| existing == root){
+            self.roots.push(root.to_path_buf());
+        }
+    }
+
+    ///Trash directory of the folder containing `path`.
+    fn get_directory(&self, path: &Path) -> PathBuf{
+        return self.roots
+            .iter()
+            .find(|root| path.starts_with(root))
+            .map(|root| root.join(TRASH_DIR))
+            .unwrap_or(self.directory.clone())
+    }
+
+    ///Trashed entries, oldest first.
+    pub(crate) fn get_entries(&self) -> &Vec<TrashEntry>{
+        return &self.entries
+    }
+
+    ///Returns true if the trashed path is part of the manifest.
+    pub(crate) fn is_recorded(&self, trashed_path: &Path) -> bool{
+        return self.entries.iter().any(|entry| entry.trashed_path == trashed_path)
+    }
+
+    ///Moves an entry into the trash directory without recording it, see `record`. Returns the trashed path.
+    pub(crate) fn move_to_trash(&self, path: &Path) -> Result<PathBuf, EditorIoError>{
+        let name = path.file_name()
+            .ok_or(EditorIoError::new("Invalid path", ErrorType::InvalidInput))?;
+
+        let directory = self.get_directory(path);
+        if !directory.exists(){
+            io::create_entry(&directory, true)?;
+        }
+        let trashed_path = io::unique_path(&directory.join(name));
+        io::rename_entry(path, &trashed_path)?;
+
+        return Ok(trashed_path)
+    }
+
+    ///Records trashed entries, as (original path, trashed path) pairs, in the manifest.
+    /// Either all of them are recorded or none.
+    pub(crate) fn record(&mut self, trashed: &[(PathBuf, PathBuf)]) -> Result<(), EditorIoError>{
+        let deleted_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        let recorded = self.entries.len();
+
+        for (original_path, trashed_path) in trashed{
+            self.entries.push(TrashEntry {
+                original_path: original_path.clone(),
+                trashed_path: trashed_path.clone(),
+                deleted_at: deleted_at
+            });
+        }
+
+        if let Err(err) = self.save(){
+            self.entries.truncate(recorded);
+            return Err(err)
+        }
+        return Ok(())
+    }
+
+    ///Moves a trashed entry back to its original path and removes it from the manifest.
+    /// Fails if the original path is taken again.
+    pub(crate) fn restore(&mut self, trashed_path: &Path) -> Result<TrashEntry, EditorIoError>{
+        let index = self.entries
+            .iter()
+            .position(|entry| entry.trashed_path == trashed_path)
+            .ok_or(EditorIoError::new("Entry not found in trash", ErrorType::NotFound))?;
+
+        io::rename_entry(trashed_path, &self.entries[index].original_path)?;
+        let entry = self.entries.remove(index);
+
+        if let Err(err) = self.save(){
+            //Keep the entry trashed, as the manifest still lists it
+            let _ = io::rename_entry(&entry.original_path, trashed_path);
+            self.entries.insert(index, entry);
+            return Err(err)
+        }
+        return Ok(entry)
+    }
+
+    ///Permanently removes every trashed entry. Entries that fail to be removed are kept.
+    pub(crate) fn empty(&mut self) -> Result<(), EditorIoError>{
+        let mut result = Ok(());
+
+        self.entries.retain(|entry| {
+            match io::remove_entry(&entry.trashed_path){
+                Ok(_) => false,
+                //Already gone
+                Err(err) if matches!(err.get_type(), ErrorType::NotFound) => false,
+                Err(err) => {
+                    result = Err(err);
+                    true
+                },
+            }
+        });
+
+        self.save()?;
+        return result
+    }
+
+    fn save(&self) -> Result<(), EditorIoError>{
+        if !self.directory.exists(){
+            io::create_entry(&self.directory, true)?;
+        }
+
+        return io::write_serialized_data(&self.entries, self.directory.join(MANIFEST_FILE))
+    }
+}
@@ -0,0 +1,180 @@
+use core::fmt;
+use std::{path::{Path, PathBuf}, sync::mpsc::{self, Receiver}, time::Duration};
+
+use notify::{event::{MetadataKind, ModifyKind, RenameMode}, Config, Event, EventKind, PollWatcher, RecursiveMode, Watcher};
+
+use crate::utils::error::EditorIoError;
+
+///Interval used by the polling fallback watcher.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+
+///Filesystem change that has to be applied to a tree structure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum TreeDelta{
+    Insert(PathBuf),
+    Remove(PathBuf),
+    Rename(PathBuf, PathBuf),
+    //Contents changed
+    Modify(PathBuf)
+}
+
+///Watches a directory recursively and collects its changes as `TreeDelta`s.
+/// Uses the platform watcher (inotify on Linux) and falls back to polling if unavailable.
+pub(crate) struct FileWatcher{
+    _watcher: Box<dyn Watcher + Send>,
+    receiver: Receiver<notify::Result<Event>>,
+    root: PathBuf
+}
+impl FileWatcher{
+    pub(crate) fn new(root: &Path) -> Result<FileWatcher, EditorIoError>{
+        let (sender, receiver) = mpsc::channel();
+
+        let native_watcher = notify::recommended_watcher(sender.clone())
+            .and_then(|mut watcher| {
+                watcher.watch(root, RecursiveMode::Recursive)?;
+                Ok(watcher)
+            });
+
+        let watcher: Box<dyn Watcher + Send> = match native_watcher{
+            Ok(watcher) => Box::new(watcher),
+            //Fallback to polling
+            Err(err) => {
+                eprintln!("Native watcher unavailable, falling back to polling: {}", err);
+                let config = Config::default().with_poll_interval(POLL_INTERVAL);
+                let mut watcher = PollWatcher::new(sender, config)?;
+                watcher.watch(root, RecursiveMode::Recursive)?;
+
+                Box::new(watcher)
+            },
+        };
+
+        return Ok(FileWatcher { 
+            _watcher: watcher, 
+            receiver: receiver, 
+            root: root.to_path_buf() 
+        })
+    }
+
+    ///Directory watched recursively.
+    pub(crate) fn get_root(&self) -> &Path{
+        return &self.root
+    }
+
+    ///Drains all pending filesystem events without blocking.
+    pub(crate) fn poll(&self) -> Vec<TreeDelta>{
+        let mut deltas = Vec::new();
+
+        while let Ok(event) = self.receiver.try_recv(){
+            match event{
+                Ok(event) => deltas.extend(event_to_deltas(event)),
+                Err(err) => eprintln!("Watcher error: {}", err),
+            }
+        }
+
+        return deltas
+    }
+}
+
+impl fmt::Debug for FileWatcher{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileWatcher")
+        .field("root", &self.root)
+        .finish()
+    }
+}
+
+///Translates a watcher event into tree deltas.
+fn event_to_deltas(event: Event) -> Vec<TreeDelta>{
+    match event.kind{
+        EventKind::Create(_) => {
+            return event.paths.into_iter().map(TreeDelta::Insert).collect()
+        },
+        EventKind::Remove(_) => {
+            return event.paths.into_iter().map(TreeDelta::Remove).collect()
+        },
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+            return vec![TreeDelta::Rename(event.paths[0].clone(), event.paths[1].clone())]
+        },
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+            return event.paths.into_iter().map(TreeDelta::Remove).collect()
+        },
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+            return event.paths.into_iter().map(TreeDelta::Insert).collect()
+        },
+        //Unknown rename side, resolve via disk
+        EventKind::Modify(ModifyKind::Name(_)) => {
+            return event.paths
+                .into_iter()
+                .map(|path| {
+                    if path.exists(){
+                        TreeDelta::Insert(path)
+                    }
+                    else{
+                        TreeDelta::Remove(path)
+                    }
+                })
+                .collect()
+        },
+        //Polling reports content changes as a new modification time
+        EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Any | ModifyKind::Metadata(MetadataKind::WriteTime)) => {
+            return event.paths.into_iter().map(TreeDelta::Modify).collect()
+        },
+        _ => return Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, DataChange, RemoveKind};
+
+    /* 
+        Translates watcher events into deltas and validates that
+            1. Creations, removals and content changes map to their delta
+            2. Renames with both sides become a single rename, single sides an insert or a removal
+            3. Unrelated events are dropped
+    */
+    #[test]
+    fn test_event_to_deltas() {
+        let from = PathBuf::from("from.txt");
+        let to = PathBuf::from("to.txt");
+        let event = |kind: EventKind, paths: &[&PathBuf]| {
+            paths.iter().fold(Event::new(kind), |event, path| event.add_path(path.to_path_buf()))
+        };
+
+        assert_eq!(event_to_deltas(event(EventKind::Create(CreateKind::File), &[&from])), vec![TreeDelta::Insert(from.clone())]);
+        assert_eq!(event_to_deltas(event(EventKind::Remove(RemoveKind::Any), &[&from])), vec![TreeDelta::Remove(from.clone())]);
+        assert_eq!(
+            event_to_deltas(event(EventKind::Modify(ModifyKind::Data(DataChange::Content)), &[&from])), 
+            vec![TreeDelta::Modify(from.clone())]
+        );
+        assert_eq!(
+            event_to_deltas(event(EventKind::Modify(ModifyKind::Metadata(MetadataKind::WriteTime)), &[&from])), 
+            vec![TreeDelta::Modify(from.clone())]
+        );
+
+        //Renames
+        assert_eq!(
+            event_to_deltas(event(EventKind::Modify(ModifyKind::Name(RenameMode::Both)), &[&from, &to])), 
+            vec![TreeDelta::Rename(from.clone(), to.clone())]
+        );
+        assert_eq!(
+            event_to_deltas(event(EventKind::Modify(ModifyKind::Name(RenameMode::From)), &[&from])), 
+            vec![TreeDelta::Remove(from.clone())]
+        );
+        assert_eq!(
+            event_to_deltas(event(EventKind::Modify(ModifyKind::Name(RenameMode::To)), &[&to])), 
+            vec![TreeDelta::Insert(to.clone())]
+        );
+        //Unknown side of a path that doesn't exist
+        assert_eq!(
+            event_to_deltas(event(EventKind::Modify(ModifyKind::Name(RenameMode::Any)), &[&from])), 
+            vec![TreeDelta::Remove(from.clone())]
+        );
+
+        //Ignored
+        assert!(event_to_deltas(event(EventKind::Access(notify::event::AccessKind::Any), &[&from])).is_empty());
+        assert!(event_to_deltas(event(EventKind::Modify(ModifyKind::Metadata(MetadataKind::Permissions)), &[&from])).is_empty());
+    }
+}